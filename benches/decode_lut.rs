@@ -0,0 +1,38 @@
+//! First-decode latency with and without `Huffman::warm`.
+//!
+//! Run with `cargo bench --bench decode_lut`.
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use huffman::Huffman;
+
+const RUNS: u32 = 50;
+
+fn main() {
+    let training = fs::read("data/S.csv").expect("Failed to read data/S.csv");
+    let message = &training[..256];
+
+    let mut cold = Duration::ZERO;
+    let mut warm = Duration::ZERO;
+
+    for _ in 0..RUNS {
+        let mut huffman = Huffman::from_bytes(training.clone());
+        let encoded = huffman.encode_bytes(message).unwrap();
+        let start = Instant::now();
+        let decoded = huffman.decode_lut(&encoded).unwrap();
+        cold += start.elapsed();
+        assert_eq!(decoded, message);
+
+        let mut huffman = Huffman::from_bytes(training.clone());
+        let encoded = huffman.encode_bytes(message).unwrap();
+        huffman.warm();
+        let start = Instant::now();
+        let decoded = huffman.decode_lut(&encoded).unwrap();
+        warm += start.elapsed();
+        assert_eq!(decoded, message);
+    }
+
+    println!("first decode, cold: {:?}", cold / RUNS);
+    println!("first decode, warm: {:?}", warm / RUNS);
+}
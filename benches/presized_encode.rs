@@ -0,0 +1,65 @@
+//! Allocations and time for encoding into a growing `BitVec` against
+//! `Huffman::encode_bytes`, which sizes it from `total_bits` first.
+//!
+//! Run with `cargo bench --bench presized_encode`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use bitvec::prelude::*;
+use huffman::Huffman;
+
+const RUNS: u32 = 50;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let input = fs::read("data/S.csv").expect("Failed to read data/S.csv");
+    let huffman = Huffman::from_bytes(input.clone());
+
+    let mut growing = (Duration::ZERO, 0);
+    let mut presized = (Duration::ZERO, 0);
+
+    for _ in 0..RUNS {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let mut grown = BitVec::new();
+        huffman.encode_into(&input, &mut grown).unwrap();
+        growing.0 += start.elapsed();
+        growing.1 += ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let sized = huffman.encode_bytes(&input).unwrap();
+        presized.0 += start.elapsed();
+        presized.1 += ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+        assert_eq!(sized, grown);
+    }
+
+    println!("growing:  {:?}, {} allocations", growing.0 / RUNS, growing.1 / RUNS as usize);
+    println!("presized: {:?}, {} allocations", presized.0 / RUNS, presized.1 / RUNS as usize);
+}
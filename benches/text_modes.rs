@@ -0,0 +1,77 @@
+//! Byte mode (`compress`) against char mode (`compress_text`) on natural
+//! language: archive sizes, ratios and compression times side by side.
+//!
+//! Run with `cargo bench --bench text_modes`.
+
+use std::time::{Duration, Instant};
+
+use huffman::{compress, compress_text};
+
+const RUNS: u32 = 20;
+
+const SAMPLES: [(&str, &str); 3] = [
+    (
+        "english",
+        "It was the best of times, it was the worst of times, it was the age of wisdom, \
+         it was the age of foolishness, it was the epoch of belief, it was the epoch of \
+         incredulity, it was the season of Light, it was the season of Darkness. ",
+    ),
+    (
+        "french",
+        "Longtemps, je me suis couché de bonne heure. Parfois, à peine ma bougie éteinte, \
+         mes yeux se fermaient si vite que je n'avais pas le temps de me dire : « Je \
+         m'endors. » Et, une demi-heure après, la pensée qu'il était temps de chercher \
+         le sommeil m'éveillait. ",
+    ),
+    (
+        "russian",
+        "Все счастливые семьи похожи друг на друга, каждая несчастливая семья несчастлива \
+         по-своему. Всё смешалось в доме Облонских. Жена узнала, что муж был в связи с \
+         бывшею в их доме француженкою-гувернанткой. ",
+    ),
+];
+
+fn time(mut compress: impl FnMut() -> Vec<u8>) -> (usize, Duration) {
+    let mut total = Duration::ZERO;
+    let mut len = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        len = compress().len();
+        total += start.elapsed();
+    }
+
+    (len, total / RUNS)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    println!("{:<8} {:>8} {:>16} {:>16}", "sample", "input", "byte mode", "char mode");
+
+    for (name, paragraph) in SAMPLES {
+        let text = paragraph.repeat(200);
+        let input_len = text.len() as f64;
+
+        let (byte_len, byte_time) = time(|| compress(text.as_bytes()).0);
+        let (char_len, char_time) = time(|| compress_text(&text));
+
+        println!(
+            "{:<8} {:>8} {:>7} ({:.3}) {:>7} ({:.3})",
+            name,
+            text.len(),
+            byte_len,
+            byte_len as f64 / input_len,
+            char_len,
+            char_len as f64 / input_len,
+        );
+        println!(
+            "{:<8} {:>8} {:>13.3}ms {:>13.3}ms",
+            "",
+            "time",
+            millis(byte_time),
+            millis(char_time),
+        );
+    }
+}
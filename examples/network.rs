@@ -0,0 +1,41 @@
+//! Sends Huffman-coded frames from a client to a server over TCP.
+//!
+//! Both ends share one codec trained up front, so frames carry only the
+//! coded bits. Run with `cargo run --example network`.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use huffman::{read_frame, write_frame, Huffman};
+
+const MESSAGES: [&str; 3] = [
+    "GET /status HTTP/1.1",
+    "status: ok, uptime: 42 days",
+    "GET /metrics HTTP/1.1",
+];
+
+fn main() -> std::io::Result<()> {
+    let training = MESSAGES.concat().into_bytes();
+    let codec = Arc::new(Huffman::from_bytes(training));
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+
+    let server_codec = Arc::clone(&codec);
+    let server = thread::spawn(move || -> std::io::Result<()> {
+        let (mut stream, _) = listener.accept()?;
+        for _ in 0..MESSAGES.len() {
+            let message = read_frame(&mut stream, &server_codec)?;
+            println!("server received: {}", String::from_utf8_lossy(&message));
+        }
+        Ok(())
+    });
+
+    let mut client = TcpStream::connect(address)?;
+    for message in MESSAGES {
+        write_frame(&mut client, &codec, message.as_bytes())?;
+    }
+
+    server.join().expect("server thread panicked")
+}
@@ -0,0 +1,39 @@
+//! Compresses many small packet payloads with one pretrained codec.
+//!
+//! The codec is trained once on representative traffic and shipped as an
+//! archive header; each packet then carries only its coded bits and a bit
+//! count. Run with `cargo run --example packets`.
+
+use huffman::{compress, load_codec, BitOrder};
+
+const TRAINING: &str = "GET /status HTTP/1.1\r\nHost: sensor-07\r\n\
+    status: ok, temp: 21.5, humidity: 40\r\n";
+
+const PACKETS: [&str; 4] = [
+    "status: ok",
+    "temp: 21.0",
+    "humidity: 40",
+    "GET /status HTTP/1.1",
+];
+
+fn main() -> Result<(), huffman::HuffmanError> {
+    // In practice the model is saved once and loaded on both ends
+    let model = compress(TRAINING.repeat(16).as_bytes()).0;
+    let codec = load_codec(&model)?;
+
+    for packet in PACKETS {
+        let encoded = codec.encode_packet(packet.as_bytes())?;
+        let payload = encoded.bytes();
+        let bit_count = encoded.bit_count();
+
+        let decoded = codec.decode_packed(payload, bit_count, BitOrder::LsbFirst)?;
+        println!(
+            "{:>22} -> {} bytes ({} bits)",
+            String::from_utf8_lossy(&decoded),
+            payload.len(),
+            bit_count,
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1533 @@
+use std::collections::HashMap;
+use std::borrow::Cow;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use bitvec::prelude::*;
+
+use crate::checksum::{crc32, Crc32};
+use crate::huffman::{Huffman, HuffmanError, HuffmanNode};
+use crate::io::{pack_bits, BitOrder, BitPacker};
+use crate::transform::{mtf_encode, MtfDecoder};
+
+
+pub const MAGIC: [u8; 4] = *b"HUFF";
+pub const VERSION: u8 = 1;
+/// Same layout as `VERSION`, but lengths are LEB128 varints.
+pub const VERSION_VARINT: u8 = 2;
+
+/// Set on the mode byte when a file name follows it.
+const NAME_FLAG: u8 = 0x80;
+/// Set on the mode byte when a symbol count follows the file name.
+const SYMBOL_COUNT_FLAG: u8 = 0x40;
+/// Set on the mode byte when permission bits follow the symbol count.
+const PERMISSIONS_FLAG: u8 = 0x20;
+/// Set on the mode byte when a CRC-32 footer follows the payload.
+const CHECKSUM_FLAG: u8 = 0x10;
+/// Extension `compress_file` appends and `decompress_file` strips.
+pub const ARCHIVE_EXTENSION: &str = "huff";
+
+/// Payload bytes `Huffman::write_archive` buffers between writes.
+const WRITE_CHUNK_LEN: usize = 8 * 1024;
+
+/// Entropy ratio above which `compress` stores data uncompressed.
+pub const INCOMPRESSIBLE_RATIO: f64 = 0.95;
+/// How many leading bytes `estimate_entropy_ratio` looks at.
+const ENTROPY_SAMPLE_LEN: usize = 4096;
+
+/// How the payload after the archive header is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveMode {
+    Huffman,
+    Stored,
+    /// Huffman-coded after a move-to-front transform.
+    MtfHuffman,
+}
+
+impl ArchiveMode {
+    fn to_byte(self) -> u8 {
+        match self {
+            ArchiveMode::Huffman => 0,
+            ArchiveMode::Stored => 1,
+            ArchiveMode::MtfHuffman => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, HuffmanError> {
+        match byte {
+            0 => Ok(ArchiveMode::Huffman),
+            1 => Ok(ArchiveMode::Stored),
+            2 => Ok(ArchiveMode::MtfHuffman),
+            _ => Err(HuffmanError::InvalidMode(byte)),
+        }
+    }
+}
+
+/// Settings for `compress_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionOptions {
+    /// Write lengths as LEB128 varints (format version 2) instead of
+    /// fixed 8-byte integers, which saves several bytes on small archives.
+    pub varint_lengths: bool,
+    /// Original file name to record in the header, so `decompress_file`
+    /// can restore it (like gzip's FNAME). Stored as a u16 length followed
+    /// by the UTF-8 bytes, right after the mode byte. Names over 65535
+    /// bytes are cut at the last whole character that fits.
+    pub file_name: Option<String>,
+    /// Zero-pad the archive to a multiple of this many bytes, for storage
+    /// that works in fixed-size blocks. The header already records the
+    /// payload length, so the padding is ignored when decompressing.
+    pub align_to: Option<usize>,
+    /// Record how many symbols the payload holds (a length, after the
+    /// file name) so decompression can check it emitted exactly that
+    /// many, failing with `SymbolCountMismatch` otherwise.
+    pub store_symbol_count: bool,
+    /// Unix permission bits (e.g. `0o644`) to record, as a u32 after the
+    /// symbol count. `compress_file` fills this in on Unix and
+    /// `decompress_file` restores it; other platforms ignore it.
+    pub permissions: Option<u32>,
+    /// Append a footer with the CRC-32 of the original data (u32 little
+    /// endian, right after the payload), which decompression checks,
+    /// failing with `ChecksumMismatch` if the data doesn't match.
+    pub checksum: bool,
+}
+
+/// Whether `compress` coded the data or fell back to storing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionMode {
+    /// Huffman-coded; `ratio` is the archive size over the input size,
+    /// header included (so tiny inputs can come out above 1.0).
+    Huffman { ratio: f64 },
+    /// Stored uncompressed, as the data looked incompressible.
+    Stored,
+}
+
+/// Compresses `data` into a self-describing archive: magic, version,
+/// mode, then either the code table, bit count and packed payload, or
+/// for stored mode the byte length and the raw data. Also reports which
+/// mode was used, for logging and metrics.
+pub fn compress(data: &[u8]) -> (Vec<u8>, CompressionMode) {
+    let mut archive = Vec::new();
+    let mode = match compress_into(data, &CompressionOptions::default(), &mut archive) {
+        ArchiveMode::Stored => CompressionMode::Stored,
+        _ => CompressionMode::Huffman { ratio: archive.len() as f64 / data.len().max(1) as f64 },
+    };
+
+    (archive, mode)
+}
+
+pub fn compress_with_options(data: &[u8], options: &CompressionOptions) -> Vec<u8> {
+    let mut archive = Vec::new();
+    compress_into(data, options, &mut archive);
+
+    archive
+}
+
+/// Compresses `data` and also returns the codec it was coded with, so
+/// the caller can decode headerless payloads or inspect the codebook
+/// without parsing the header back. Unlike `compress`, the data is always
+/// Huffman-coded, never stored, so the payload matches the codec.
+pub fn compress_with_codec(data: &[u8]) -> (Vec<u8>, Huffman) {
+    let huffman = Huffman::from_counts(Vec::new(), HuffmanNode::count_bytes(data));
+    let archive = huffman
+        .to_bytes(data)
+        .expect("table built from the data covers every byte");
+
+    (archive, huffman)
+}
+
+/// Compresses `data`, failing with `RatioNotMet` if it doesn't shrink by
+/// at least `min_ratio` (input size over archive size, so 2.0 means half
+/// the size), letting the caller fall back to another algorithm. This is
+/// the inverse of the ratio `compress` reports; the error's `achieved`
+/// gives the ratio reached. Empty input can't meet any ratio above 0.
+pub fn compress_with_target(data: &[u8], min_ratio: f64) -> Result<Vec<u8>, HuffmanError> {
+    let archive = compress(data).0;
+    let achieved = data.len() as f64 / archive.len() as f64;
+    if achieved < min_ratio {
+        return Err(HuffmanError::RatioNotMet { archive_len: archive.len(), input_len: data.len() });
+    }
+
+    Ok(archive)
+}
+
+/// Compresses bytes from any iterator, e.g. a generator or channel
+/// receiver. The bytes are buffered, since building the table needs a
+/// frequency pass before anything can be encoded.
+pub fn compress_iter<I: IntoIterator<Item = u8>>(iter: I) -> Vec<u8> {
+    let data: Vec<u8> = iter.into_iter().collect();
+
+    compress(&data).0
+}
+
+/// Writes the archive for `data` into `scratch`, replacing its contents,
+/// and returns the archive length. Reusing one buffer across calls avoids
+/// reallocating for every message.
+pub fn compress_with_scratch(data: &[u8], scratch: &mut Vec<u8>) -> usize {
+    scratch.clear();
+    compress_into(data, &CompressionOptions::default(), scratch);
+
+    scratch.len()
+}
+
+fn compress_into(data: &[u8], options: &CompressionOptions, archive: &mut Vec<u8>)
+    -> ArchiveMode {
+    let start = archive.len();
+
+    // Don't bother building a tree for data that won't shrink
+    let mode = if estimate_entropy_ratio(data) > INCOMPRESSIBLE_RATIO {
+        write_stored(archive, options, data);
+        ArchiveMode::Stored
+    } else {
+        // Encode from the borrowed slice rather than copying it into the codec
+        let huffman = Huffman::from_counts(Vec::new(), HuffmanNode::count_bytes(data));
+        let encoded = huffman
+            .encode_bytes(data)
+            .expect("table built from the data covers every byte");
+
+        write_archive(archive, options, ArchiveMode::Huffman, huffman.table(), &encoded, data.len());
+        ArchiveMode::Huffman
+    };
+    write_checksum(archive, options, crc32(data));
+
+    if let Some(align) = options.align_to.filter(|align| *align > 0) {
+        let len = archive.len() - start;
+        archive.resize(start + len.next_multiple_of(align), 0);
+    }
+
+    mode
+}
+
+/// Compresses `data` after a move-to-front transform (see `mtf_encode`),
+/// which helps on data where the same few bytes repeat close together.
+/// The mode byte records the transform so `decompress` undoes it.
+pub fn compress_with_mtf(data: &[u8]) -> Vec<u8> {
+    let transformed = mtf_encode(data);
+    let mut archive = Vec::new();
+
+    let options = CompressionOptions::default();
+
+    if estimate_entropy_ratio(&transformed) > INCOMPRESSIBLE_RATIO {
+        write_stored(&mut archive, &options, data);
+        write_checksum(&mut archive, &options, crc32(data));
+        return archive;
+    }
+
+    let mut huffman = Huffman::from_bytes(transformed);
+    let encoded = huffman
+        .encode()
+        .expect("table built from the data covers every byte");
+    write_archive(&mut archive, &options, ArchiveMode::MtfHuffman, huffman.table(), &encoded,
+        data.len());
+    write_checksum(&mut archive, &options, crc32(data));
+
+    archive
+}
+
+impl Huffman {
+    /// Encodes `data` with this codec's table and wraps it in the same
+    /// self-describing archive `compress` writes, table included. Fails
+    /// if `data` has a byte the table has no code for.
+    pub fn to_bytes(&self, data: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        if self.has_reserved() {
+            return Err(HuffmanError::ReservedBytes);
+        }
+
+        let encoded = self.encode_bytes(data)?;
+        let mut archive = Vec::new();
+        write_archive(&mut archive, &CompressionOptions::default(), ArchiveMode::Huffman,
+            self.table(), &encoded, data.len());
+
+        Ok(archive)
+    }
+
+    /// Streams the same archive as `to_bytes` into `writer`: the header
+    /// first, then the payload packed and flushed in chunks, so the whole
+    /// archive never sits in memory. Makes one pass over `data` to work
+    /// out the bit count for the header and another to encode it.
+    pub fn write_archive<W: Write>(&self, data: &[u8], writer: &mut W) -> std::io::Result<()> {
+        self.write_archive_with_options(data, &CompressionOptions::default(), writer)
+    }
+
+    /// Like `write_archive`, with header options. With `checksum` set the
+    /// CRC-32 is updated in the encode pass, so it costs no extra pass.
+    /// `align_to` is ignored, as the archive length isn't known up front.
+    pub fn write_archive_with_options<W: Write>(&self, data: &[u8], options: &CompressionOptions,
+        writer: &mut W) -> std::io::Result<()> {
+        if self.has_reserved() {
+            return Err(HuffmanError::ReservedBytes.into());
+        }
+        let bit_count = self.total_bits(data)?;
+
+        let mut header = Vec::new();
+        write_prelude(&mut header, options, ArchiveMode::Huffman, data.len());
+        write_table(&mut header, self.table());
+        write_length(&mut header, options, bit_count);
+        writer.write_all(&header)?;
+
+        let mut chunk = Vec::with_capacity(WRITE_CHUNK_LEN);
+        let mut packer = BitPacker::new(BitOrder::LsbFirst);
+        let mut crc = Crc32::new();
+
+        for byte in data {
+            crc.push(*byte);
+            for bit in self.table()[byte].iter().by_vals() {
+                packer.push(bit, &mut chunk);
+            }
+
+            if chunk.len() >= WRITE_CHUNK_LEN {
+                writer.write_all(&chunk)?;
+                chunk.clear();
+            }
+        }
+        packer.finish(&mut chunk);
+        write_checksum(&mut chunk, options, crc.finish());
+
+        writer.write_all(&chunk)
+    }
+
+    /// Size in bytes of this codec's table as written in an archive
+    /// header, worked out from the code lengths without serializing it.
+    /// Both format versions store the table the same way. With
+    /// `total_bits`, gives the exact size of `to_bytes` up front: magic,
+    /// version and mode bytes, the table, an 8-byte bit count, then
+    /// `total_bits / 8` rounded up.
+    pub fn code_table_bytes(&self) -> usize {
+        let entries: usize = self.table().values().map(|code| 2 + code.len().div_ceil(8)).sum();
+
+        2 + entries
+    }
+
+    /// Decodes an archive from `to_bytes` (or any other archive); the
+    /// table comes from the header, so no codec is needed.
+    pub fn archive_to_data(bytes: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        decompress(bytes)
+    }
+}
+
+/// Estimates how incompressible `data` is from the Shannon entropy of its
+/// first few KiB, as a fraction of 8 bits per byte: near 0.0 for highly
+/// repetitive data, near 1.0 for random or already-compressed data.
+pub fn estimate_entropy_ratio(data: &[u8]) -> f64 {
+    byte_entropy(&data[..data.len().min(ENTROPY_SAMPLE_LEN)]) / 8.0
+}
+
+/// Shannon entropy of `data` in bits per byte.
+pub(crate) fn byte_entropy(data: &[u8]) -> f64 {
+    HuffmanNode::entropy(&HuffmanNode::count_bytes(data))
+}
+
+fn write_archive(archive: &mut Vec<u8>, options: &CompressionOptions, mode: ArchiveMode,
+    table: &HashMap<u8, BitVec>, encoded: &BitVec, symbol_count: usize) {
+    write_prelude(archive, options, mode, symbol_count);
+    write_table(archive, table);
+    write_length(archive, options, encoded.len());
+    archive.extend(pack_bits(encoded));
+}
+
+fn write_stored(archive: &mut Vec<u8>, options: &CompressionOptions, data: &[u8]) {
+    write_prelude(archive, options, ArchiveMode::Stored, data.len());
+    write_length(archive, options, data.len());
+    archive.extend_from_slice(data);
+}
+
+fn write_checksum(archive: &mut Vec<u8>, options: &CompressionOptions, crc: u32) {
+    if options.checksum {
+        archive.extend_from_slice(&crc.to_le_bytes());
+    }
+}
+
+fn write_prelude(archive: &mut Vec<u8>, options: &CompressionOptions, mode: ArchiveMode,
+    symbol_count: usize) {
+    archive.extend_from_slice(&MAGIC);
+    archive.push(if options.varint_lengths { VERSION_VARINT } else { VERSION });
+
+    let mut mode_byte = mode.to_byte();
+    if options.file_name.is_some() {
+        mode_byte |= NAME_FLAG;
+    }
+    if options.store_symbol_count {
+        mode_byte |= SYMBOL_COUNT_FLAG;
+    }
+    if options.permissions.is_some() {
+        mode_byte |= PERMISSIONS_FLAG;
+    }
+    if options.checksum {
+        mode_byte |= CHECKSUM_FLAG;
+    }
+    archive.push(mode_byte);
+
+    if let Some(name) = &options.file_name {
+        let name = &name[..name.floor_char_boundary(u16::MAX as usize)];
+        archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(name.as_bytes());
+    }
+    if options.store_symbol_count {
+        write_length(archive, options, symbol_count);
+    }
+    if let Some(permissions) = options.permissions {
+        archive.extend_from_slice(&permissions.to_le_bytes());
+    }
+}
+
+fn write_length(archive: &mut Vec<u8>, options: &CompressionOptions, len: usize) {
+    if options.varint_lengths {
+        write_varint(archive, len as u64);
+    } else {
+        archive.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+}
+
+/// LEB128: seven bits per byte, low bits first, high bit set on every
+/// byte but the last.
+pub(crate) fn write_varint(archive: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        archive.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    archive.push(value as u8);
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    decompress_into(bytes)
+}
+
+/// Like `decompress`, but a stored archive's data is returned as a slice
+/// of `bytes` rather than copied, since there is nothing to decode.
+/// Huffman archives are decoded into an owned buffer as usual.
+pub fn decompress_borrowed(bytes: &[u8]) -> Result<Cow<'_, [u8]>, HuffmanError> {
+    let (header, header_len) = read_header(bytes)?;
+    if header.mode != ArchiveMode::Stored {
+        return decompress(bytes).map(Cow::Owned);
+    }
+
+    let mut reader = ByteReader::new(&bytes[header_len..]);
+    let payload = reader.take(header.bit_count / 8)?;
+    check_symbol_count(header.symbol_count, payload.len())?;
+    check_footer(header.checksum, &mut reader, crc32(payload))?;
+
+    Ok(Cow::Borrowed(payload))
+}
+
+/// Like `decompress`, but decodes into any collection that can be
+/// extended with bytes (a `SmallVec`, `BytesMut`, or custom buffer)
+/// instead of a `Vec<u8>`. Bytes are appended as they are decoded.
+pub fn decompress_into<C: Extend<u8> + Default>(bytes: &[u8]) -> Result<C, HuffmanError> {
+    let (data, _) = read_archive(bytes)?;
+
+    Ok(data)
+}
+
+/// Compresses `data` and encodes the archive as lowercase hex, for
+/// storing in text-only places without the `base64` feature.
+pub fn compress_to_hex(data: &[u8]) -> String {
+    compress(data).0.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Accepts upper- or lowercase hex.
+pub fn decompress_from_hex(s: &str) -> Result<Vec<u8>, HuffmanError> {
+    if !s.len().is_multiple_of(2) {
+        return Err(HuffmanError::InvalidHex);
+    }
+
+    let digit = |c: u8| (c as char).to_digit(16).ok_or(HuffmanError::InvalidHex);
+    let archive = s
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    decompress(&archive)
+}
+
+/// Compresses `data` and encodes the archive as URL-safe base64 without
+/// padding, for embedding in JSON or URLs.
+#[cfg(feature = "base64")]
+pub fn compress_to_base64(data: &[u8]) -> String {
+    use base64::Engine;
+
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(compress(data).0)
+}
+
+#[cfg(feature = "base64")]
+pub fn decompress_from_base64(s: &str) -> Result<Vec<u8>, HuffmanError> {
+    use base64::Engine;
+
+    let archive = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(s)
+        .map_err(|_| HuffmanError::InvalidBase64)?;
+
+    decompress(&archive)
+}
+
+/// Decompresses every archive in `bytes`, e.g. several `.huff` files
+/// that were concatenated together. Zero bytes between archives are
+/// skipped as alignment padding (see `CompressionOptions::align_to`).
+pub fn decompress_all(bytes: &[u8]) -> Result<Vec<Vec<u8>>, HuffmanError> {
+    let mut outputs = Vec::new();
+    let mut rest = bytes;
+
+    loop {
+        let padding = rest.iter().take_while(|byte| **byte == 0).count();
+        rest = &rest[padding..];
+        if rest.is_empty() {
+            break;
+        }
+
+        let (data, consumed) = read_archive(rest)?;
+        outputs.push(data);
+        rest = &rest[consumed..];
+    }
+
+    Ok(outputs)
+}
+
+/// Decompresses each archive and recompresses their concatenated data
+/// into one archive with a single combined table, which is often smaller
+/// than keeping a header per archive.
+pub fn merge_archives(archives: &[Vec<u8>]) -> Result<Vec<u8>, HuffmanError> {
+    let mut data = Vec::new();
+    for archive in archives {
+        data.extend(decompress(archive)?);
+    }
+
+    Ok(compress(&data).0)
+}
+
+/// Mode, code table and payload length parsed from an archive header.
+/// Stored archives have an empty table and a bit count of 8 per byte.
+pub(crate) struct Header {
+    pub mode: ArchiveMode,
+    pub file_name: Option<String>,
+    pub symbol_count: Option<usize>,
+    pub permissions: Option<u32>,
+    /// Whether a CRC-32 footer follows the payload.
+    pub checksum: bool,
+    pub table: HashMap<BitVec<u8>, u8>,
+    pub bit_count: usize,
+}
+
+/// Parses the header at the front of `bytes`, returning it along with the
+/// number of bytes it occupied. Fails with `Truncated` if `bytes` ends
+/// before the header does.
+pub(crate) fn read_header(bytes: &[u8]) -> Result<(Header, usize), HuffmanError> {
+    let mut reader = ByteReader::new(bytes);
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(HuffmanError::InvalidMagic);
+    }
+
+    let version = reader.take_u8()?;
+    let read_length = match version {
+        VERSION => ByteReader::take_u64,
+        VERSION_VARINT => ByteReader::take_varint,
+        _ => return Err(HuffmanError::UnsupportedVersion(version)),
+    };
+
+    let mode_byte = reader.take_u8()?;
+    let flags = NAME_FLAG | SYMBOL_COUNT_FLAG | PERMISSIONS_FLAG | CHECKSUM_FLAG;
+    let mode = ArchiveMode::from_byte(mode_byte & !flags)?;
+    let checksum = mode_byte & CHECKSUM_FLAG != 0;
+
+    let file_name = if mode_byte & NAME_FLAG != 0 {
+        let len = reader.take_u16()? as usize;
+        let name = String::from_utf8(reader.take(len)?.to_vec())
+            .map_err(|_| HuffmanError::InvalidFileName)?;
+        Some(name)
+    } else {
+        None
+    };
+
+    let symbol_count = if mode_byte & SYMBOL_COUNT_FLAG != 0 {
+        Some(read_length(&mut reader)? as usize)
+    } else {
+        None
+    };
+
+    let permissions = if mode_byte & PERMISSIONS_FLAG != 0 {
+        Some(reader.take_u32()?)
+    } else {
+        None
+    };
+
+    let header = match mode {
+        ArchiveMode::Huffman | ArchiveMode::MtfHuffman => {
+            let table = read_table(&mut reader)?;
+            let bit_count = read_length(&mut reader)? as usize;
+            Header { mode, file_name, symbol_count, permissions, checksum, table, bit_count }
+        }
+        ArchiveMode::Stored => {
+            let len = read_length(&mut reader)? as usize;
+            let bit_count = len.checked_mul(8).ok_or(HuffmanError::Truncated)?;
+            let table = HashMap::new();
+            Header { mode, file_name, symbol_count, permissions, checksum, table, bit_count }
+        }
+    };
+
+    Ok((header, reader.pos))
+}
+
+/// Compresses the file at `path` into `<path>.huff`, recording its file
+/// name in the header, and returns the archive path.
+///
+/// With `delete_original`, the file is then removed like `gzip` does, but
+/// only once the archive has been read back from disk and verified to
+/// decompress to the original data.
+pub fn compress_file(path: &Path, delete_original: bool) -> std::io::Result<PathBuf> {
+    let data = fs::read(path)?;
+
+    let mut archive_path = path.as_os_str().to_owned();
+    archive_path.push(".");
+    archive_path.push(ARCHIVE_EXTENSION);
+    let archive_path = PathBuf::from(archive_path);
+
+    fs::write(&archive_path, compress_with_options(&data, &file_options(path)))?;
+
+    if delete_original {
+        verify(&archive_path, &data)?;
+        fs::remove_file(path)?;
+    }
+
+    Ok(archive_path)
+}
+
+/// Fails with `InvalidData` unless the archive at `archive_path`
+/// decompresses to `expected`.
+fn verify(archive_path: &Path, expected: &[u8]) -> std::io::Result<()> {
+    if decompress(&fs::read(archive_path)?)? != expected {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "archive doesn't decompress to the original data",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compresses only bytes `start..start + len` of the file at `input`
+/// into an archive at `output`, seeking past the rest rather than reading
+/// it. Fails with `UnexpectedEof` if the file ends before the region does.
+pub fn compress_file_region(input: &Path, output: &Path, start: u64, len: usize)
+    -> std::io::Result<()> {
+    let mut file = fs::File::open(input)?;
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut region = vec![0u8; len];
+    file.read_exact(&mut region)?;
+
+    fs::write(output, compress(&region).0)
+}
+
+/// Like `compress_file`, but memory-maps `input` and compresses straight
+/// from the mapping instead of reading it into a `Vec`, so large files
+/// aren't held in memory twice. Writes the archive to `output`.
+#[cfg(feature = "mmap")]
+pub fn compress_file_mmap(input: &Path, output: &Path) -> std::io::Result<()> {
+    let file = fs::File::open(input)?;
+    // Safety: the mapping is only read while this function runs; another
+    // process truncating the file meanwhile is outside what we guard against
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+
+    fs::write(output, compress_with_options(&map, &file_options(input)))
+}
+
+/// Options recording `path`'s file name, and on Unix its permissions,
+/// in the header.
+fn file_options(path: &Path) -> CompressionOptions {
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::metadata(path).ok().map(|meta| meta.permissions().mode() & 0o777)
+    };
+    #[cfg(not(unix))]
+    let permissions = None;
+
+    CompressionOptions {
+        file_name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
+        permissions,
+        ..Default::default()
+    }
+}
+
+/// Decompresses the archive at `path` and returns where the data was
+/// written. Without an explicit `output`, the file name stored in the
+/// header is used, placed next to the archive; archives without one fall
+/// back to `path` with its `.huff` extension removed.
+pub fn decompress_file(path: &Path, output: Option<&Path>) -> std::io::Result<PathBuf> {
+    let archive = fs::read(path)?;
+    let (header, _) = read_header(&archive)?;
+    let (data, _) = read_archive::<Vec<u8>>(&archive)?;
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => default_output_path(path, header.file_name.as_deref())?,
+    };
+    fs::write(&output, data)?;
+
+    // Only the rwx bits are restored, so an archive can't hand out setuid
+    // or setgid files
+    #[cfg(unix)]
+    if let Some(permissions) = header.permissions {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&output, fs::Permissions::from_mode(permissions & 0o777))?;
+    }
+
+    Ok(output)
+}
+
+fn default_output_path(path: &Path, stored_name: Option<&str>) -> std::io::Result<PathBuf> {
+    // Only the final component is trusted, so a crafted name can't point
+    // outside the archive's directory
+    if let Some(name) = stored_name.map(Path::new).and_then(Path::file_name) {
+        return Ok(path.with_file_name(name));
+    }
+
+    if path.extension().is_some_and(|ext| ext == ARCHIVE_EXTENSION) {
+        return Ok(path.with_extension(""));
+    }
+
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "archive has no stored file name and no .huff extension",
+    ))
+}
+
+/// Parses the code table from an archive header, in the same byte → code
+/// form as `Huffman::table`, for inspecting what a decoder will use.
+/// Anything after the header is ignored.
+pub fn reconstruct_table(header_bytes: &[u8]) -> Result<HashMap<u8, BitVec>, HuffmanError> {
+    let (header, _) = read_header(header_bytes)?;
+
+    Ok(header
+        .table
+        .into_iter()
+        .map(|(code, byte)| (byte, code.iter().by_vals().collect()))
+        .collect())
+}
+
+/// Loads a pretrained codec from the header of an archive, e.g. one made
+/// by compressing representative training data, so it can be shared to
+/// code many small messages without a table each (see
+/// `Huffman::encode_packet`). Anything after the header is ignored.
+pub fn load_codec(header_bytes: &[u8]) -> Result<Huffman, HuffmanError> {
+    Huffman::from_table(reconstruct_table(header_bytes)?)
+}
+
+/// Reads one archive from the front of `bytes`, returning the decoded
+/// data and the number of bytes the archive occupied.
+fn read_archive<C: Extend<u8> + Default>(bytes: &[u8]) -> Result<(C, usize), HuffmanError> {
+    let (header, header_len) = read_header(bytes)?;
+    let mut reader = ByteReader::new(&bytes[header_len..]);
+    let payload = reader.take(header.bit_count.div_ceil(8))?;
+
+    let mut data = C::default();
+    if header.mode == ArchiveMode::Stored {
+        check_symbol_count(header.symbol_count, payload.len())?;
+        check_footer(header.checksum, &mut reader, crc32(payload))?;
+        data.extend(payload.iter().copied());
+        return Ok((data, header_len + reader.pos));
+    }
+
+    let mut bits = BitVec::<u8>::from_slice(payload);
+    bits.truncate(header.bit_count);
+
+    let mut mtf = (header.mode == ArchiveMode::MtfHuffman).then(MtfDecoder::new);
+    let mut crc = Crc32::new();
+    let symbols = decode_payload(header.table, &bits, |byte| {
+        let byte = match &mut mtf {
+            Some(mtf) => mtf.decode(byte),
+            None => byte,
+        };
+        crc.push(byte);
+        data.extend(std::iter::once(byte));
+    })?;
+    check_symbol_count(header.symbol_count, symbols)?;
+    check_footer(header.checksum, &mut reader, crc.finish())?;
+
+    Ok((data, header_len + reader.pos))
+}
+
+/// Reads the CRC-32 footer, if the header says there is one, and fails
+/// with `ChecksumMismatch` if it isn't `actual`.
+fn check_footer(present: bool, reader: &mut ByteReader, actual: u32)
+    -> Result<(), HuffmanError> {
+    if present {
+        check_checksum(reader.take_u32()?, actual)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_checksum(expected: u32, actual: u32) -> Result<(), HuffmanError> {
+    if expected != actual {
+        return Err(HuffmanError::ChecksumMismatch { expected, actual });
+    }
+
+    Ok(())
+}
+
+/// Fails with `SymbolCountMismatch` if the header stored a symbol count
+/// and `actual` differs from it.
+pub(crate) fn check_symbol_count(expected: Option<usize>, actual: usize)
+    -> Result<(), HuffmanError> {
+    match expected {
+        Some(expected) if expected != actual => {
+            Err(HuffmanError::SymbolCountMismatch { expected, actual })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn write_table(archive: &mut Vec<u8>, table: &HashMap<u8, BitVec>) {
+    let mut entries: Vec<_> = table.iter().collect();
+    entries.sort_by_key(|entry| *entry.0);
+
+    archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (byte, code) in entries {
+        archive.push(*byte);
+        archive.push(code.len() as u8);
+        archive.extend(pack_bits(code));
+    }
+}
+
+pub(crate) fn read_table(reader: &mut ByteReader) -> Result<HashMap<BitVec<u8>, u8>, HuffmanError> {
+    let count = reader.take_u16()? as usize;
+    if count > 256 {
+        return Err(HuffmanError::InvalidTable);
+    }
+
+    let mut table = HashMap::new();
+    for _ in 0..count {
+        let byte = reader.take_u8()?;
+        let code_len = reader.take_u8()? as usize;
+        if code_len == 0 {
+            return Err(HuffmanError::InvalidTable);
+        }
+
+        let mut code = BitVec::<u8>::from_slice(reader.take(code_len.div_ceil(8))?);
+        code.truncate(code_len);
+
+        if table.insert(code, byte).is_some() {
+            return Err(HuffmanError::InvalidTable);
+        }
+    }
+
+    Ok(table)
+}
+
+/// Passes each decoded byte to `emit` and returns how many there were.
+fn decode_payload(table: HashMap<BitVec<u8>, u8>, bits: &BitSlice<u8>,
+    mut emit: impl FnMut(u8)) -> Result<usize, HuffmanError> {
+    let mut decoder = PayloadDecoder::new(table);
+    let mut symbols = 0;
+
+    for bit in bits.iter().by_vals() {
+        if let Some(byte) = decoder.push_bit(bit)? {
+            emit(byte);
+            symbols += 1;
+        }
+    }
+    decoder.finish()?;
+
+    Ok(symbols)
+}
+
+/// Matches payload bits against a code table one bit at a time, holding
+/// on to a partial code between calls.
+pub(crate) struct PayloadDecoder<T = u8> {
+    table: HashMap<BitVec<u8>, T>,
+    max_len: usize,
+    current: BitVec<u8>,
+    position: usize,
+}
+
+impl<T: Copy> PayloadDecoder<T> {
+    pub fn new(table: HashMap<BitVec<u8>, T>) -> Self {
+        let max_len = table.keys().map(|code| code.len()).max().unwrap_or(0);
+
+        PayloadDecoder {
+            table,
+            max_len,
+            current: BitVec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn push_bit(&mut self, bit: bool) -> Result<Option<T>, HuffmanError> {
+        self.current.push(bit);
+        self.position += 1;
+
+        if let Some(symbol) = self.table.get(&self.current) {
+            self.current.clear();
+            Ok(Some(*symbol))
+        } else if self.current.len() >= self.max_len {
+            Err(HuffmanError::InvalidCode)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fails if the payload ended partway through a code, reporting how
+    /// many bits had been read.
+    pub fn finish(&self) -> Result<(), HuffmanError> {
+        if self.current.is_empty() {
+            Ok(())
+        } else {
+            Err(HuffmanError::IncompleteCode { at_bit: self.position })
+        }
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, pos: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], HuffmanError> {
+        let end = self.pos.checked_add(len).ok_or(HuffmanError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(HuffmanError::Truncated)?;
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    pub fn take_u8(&mut self) -> Result<u8, HuffmanError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn take_u16(&mut self) -> Result<u16, HuffmanError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn take_u32(&mut self) -> Result<u32, HuffmanError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn take_u64(&mut self) -> Result<u64, HuffmanError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a LEB128 varint written by `write_varint`. Fails with
+    /// `InvalidVarint` if it runs past 64 bits: more than ten bytes, or a
+    /// tenth byte above 1.
+    pub fn take_varint(&mut self) -> Result<u64, HuffmanError> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take_u8()?;
+            if shift == 63 && byte > 1 {
+                return Err(HuffmanError::InvalidVarint);
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(HuffmanError::InvalidVarint)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::HuffmanNode;
+
+    #[test]
+    fn compress_decompress_roundtrip() {
+        let input = b"hello world";
+        let archive = compress(input).0;
+
+        assert_eq!(&archive[..4], &MAGIC);
+        assert_eq!(decompress(&archive).unwrap(), input);
+    }
+
+    #[test]
+    fn empty_archive_roundtrip() {
+        let archive = compress(b"").0;
+
+        assert_eq!(decompress(&archive).unwrap(), b"");
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut archive = compress(b"hello").0;
+        archive[0] = b'X';
+
+        assert_eq!(decompress(&archive), Err(HuffmanError::InvalidMagic));
+    }
+
+    #[test]
+    fn rejects_truncated_archive() {
+        let archive = compress(b"hello world").0;
+
+        assert_eq!(
+            decompress(&archive[..archive.len() - 1]),
+            Err(HuffmanError::Truncated)
+        );
+    }
+
+    #[test]
+    fn decompress_all_concatenated_archives() {
+        let mut stream = compress(b"first archive").0;
+        stream.extend(compress(b"second, different archive").0);
+
+        let outputs = decompress_all(&stream).unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0], b"first archive");
+        assert_eq!(outputs[1], b"second, different archive");
+    }
+
+    #[test]
+    fn compress_with_reused_scratch_buffer() {
+        let messages: [&[u8]; 4] = [
+            b"a fairly long first message to grow the buffer",
+            b"short",
+            b"",
+            b"zzzzzzzzzz",
+        ];
+        let mut scratch = Vec::new();
+
+        for message in messages {
+            let len = compress_with_scratch(message, &mut scratch);
+
+            assert_eq!(len, scratch.len());
+            assert_eq!(decompress(&scratch[..len]).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn reconstructed_table_matches_compression_table() {
+        let input = b"reconstruct the table from the header".to_vec();
+        let mut huffman = Huffman::from_bytes(input.clone());
+        let encoded = huffman.encode().unwrap();
+
+        let mut archive = Vec::new();
+        let options = CompressionOptions::default();
+        write_archive(&mut archive, &options, ArchiveMode::Huffman, huffman.table(), &encoded,
+            input.len());
+
+        assert_eq!(&reconstruct_table(&archive).unwrap(), huffman.table());
+    }
+
+    #[test]
+    fn deep_table_header_rebuilds_same_tree() {
+        // A chain as deep as a u8 code length allows: 1, 01, 001, ...
+        let mut table = HashMap::new();
+        for depth in 0..255 {
+            let mut code = bitvec![0; depth];
+            code.push(true);
+            table.insert(depth as u8, code);
+        }
+        table.insert(255, bitvec![0; 255]);
+
+        let mut archive = Vec::new();
+        let options = CompressionOptions::default();
+        write_archive(&mut archive, &options, ArchiveMode::Huffman, &table, &bitvec![], 0);
+
+        let reconstructed = reconstruct_table(&archive).unwrap();
+        assert_eq!(reconstructed, table);
+
+        let tree = HuffmanNode::from_table(&reconstructed).unwrap();
+        assert_eq!(tree.height(), 255);
+
+        let mut rebuilt = HashMap::new();
+        Huffman::make_table(&mut rebuilt, &tree, &mut bitvec![]);
+        assert_eq!(rebuilt, table);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64_roundtrip_is_url_safe() {
+        let input = b"base64 \xff\xfe\xfd round trip ???>>>".repeat(4);
+        let encoded = compress_to_base64(&input);
+
+        assert!(encoded
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'));
+        assert_eq!(decompress_from_base64(&encoded).unwrap(), input);
+        assert_eq!(decompress_from_base64("not base64!"), Err(HuffmanError::InvalidBase64));
+    }
+
+    /// Deterministic pseudo-random bytes (xorshift64).
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn entropy_ratio_separates_random_from_repetitive() {
+        assert!(estimate_entropy_ratio(&noise(8192)) > INCOMPRESSIBLE_RATIO);
+        assert!(estimate_entropy_ratio(&b"abab".repeat(100)) < 0.2);
+        assert_eq!(estimate_entropy_ratio(&[7; 100]), 0.0);
+        assert_eq!(estimate_entropy_ratio(b""), 0.0);
+    }
+
+    #[test]
+    fn random_data_is_stored_without_a_tree() {
+        let input = noise(8192);
+        let archive = compress(&input).0;
+
+        // Magic, version, mode byte, u64 length, then the raw bytes
+        assert_eq!(archive[MAGIC.len() + 1], ArchiveMode::Stored.to_byte());
+        assert_eq!(archive.len(), MAGIC.len() + 2 + 8 + input.len());
+        assert_eq!(&archive[MAGIC.len() + 10..], &input[..]);
+        assert_eq!(decompress(&archive).unwrap(), input);
+    }
+
+    #[test]
+    fn mtf_beats_plain_huffman_on_local_repetition() {
+        let data: Vec<u8> = (0..40)
+            .flat_map(|round| (b'a'..=b'p').flat_map(move |byte| vec![byte; 3 + round % 5]))
+            .collect();
+
+        let plain = compress(&data).0;
+        let with_mtf = compress_with_mtf(&data);
+
+        assert_eq!(with_mtf[MAGIC.len() + 1], ArchiveMode::MtfHuffman.to_byte());
+        assert!(with_mtf.len() < plain.len());
+        assert_eq!(decompress(&with_mtf).unwrap(), data);
+    }
+
+    #[test]
+    fn varint_roundtrip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+
+            let mut reader = ByteReader::new(&bytes);
+            assert_eq!(reader.take_varint().unwrap(), value);
+            assert_eq!(reader.pos, bytes.len());
+        }
+
+        let high_bits = [[0xff; 9].as_slice(), &[0x02]].concat();
+        assert_eq!(ByteReader::new(&high_bits).take_varint(), Err(HuffmanError::InvalidVarint));
+        let too_long = [0x80; 11];
+        assert_eq!(ByteReader::new(&too_long).take_varint(), Err(HuffmanError::InvalidVarint));
+    }
+
+    #[test]
+    fn varint_lengths_shrink_small_archives() {
+        let options = CompressionOptions { varint_lengths: true, ..Default::default() };
+
+        for input in [&b"tiny"[..], b"", &noise(64)] {
+            let fixed = compress(input).0;
+            let varint = compress_with_options(input, &options);
+
+            assert_eq!(varint[MAGIC.len()], VERSION_VARINT);
+            assert!(varint.len() < fixed.len());
+            assert_eq!(decompress(&varint).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn decompress_file_restores_stored_name() {
+        let dir = std::env::temp_dir().join(format!("huffman-fname-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("foo.txt");
+        fs::write(&original, b"restore me by name").unwrap();
+
+        let archive = compress_file(&original, false).unwrap();
+        let renamed = dir.join("renamed.huff");
+        fs::rename(&archive, &renamed).unwrap();
+        fs::remove_file(&original).unwrap();
+
+        let restored = decompress_file(&renamed, None).unwrap();
+        assert_eq!(restored, original);
+        assert_eq!(fs::read(&original).unwrap(), b"restore me by name");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_name_header_roundtrip() {
+        let options = CompressionOptions { file_name: Some("notes.md".into()), ..Default::default() };
+        let archive = compress_with_options(b"named data", &options);
+
+        let (header, _) = read_header(&archive).unwrap();
+        assert_eq!(header.file_name.as_deref(), Some("notes.md"));
+        assert_eq!(decompress(&archive).unwrap(), b"named data");
+        assert_eq!(default_output_path(Path::new("x/a.huff"), Some("../../etc/passwd")).unwrap(),
+            Path::new("x/passwd"));
+    }
+
+    #[test]
+    fn long_file_name_is_cut_at_char_boundary() {
+        let name = "é".repeat(40000);
+        let options = CompressionOptions { file_name: Some(name.clone()), ..Default::default() };
+        let archive = compress_with_options(b"named data", &options);
+
+        let (header, _) = read_header(&archive).unwrap();
+        let stored = header.file_name.unwrap();
+        assert_eq!(stored.len(), u16::MAX as usize - 1);
+        assert!(name.starts_with(&stored));
+        assert_eq!(decompress(&archive).unwrap(), b"named data");
+    }
+
+    #[test]
+    fn compress_iter_roundtrip() {
+        let archive = compress_iter((0u8..=255).cycle().take(1000));
+        let expected: Vec<u8> = (0u8..=255).cycle().take(1000).collect();
+
+        assert_eq!(decompress(&archive).unwrap(), expected);
+    }
+
+    #[test]
+    fn merged_archive_is_concatenation() {
+        let first = b"merging archives shares one table. ".repeat(20);
+        let second = b"the second archive has similar text in it. ".repeat(20);
+        let archives = vec![compress(&first).0, compress(&second).0];
+
+        let merged = merge_archives(&archives).unwrap();
+
+        assert_eq!(decompress(&merged).unwrap(), [first, second].concat());
+        assert!(merged.len() <= archives.iter().map(Vec::len).sum());
+    }
+
+    #[test]
+    fn aligned_archive_is_padded_to_block_size() {
+        let options = CompressionOptions { align_to: Some(512), ..Default::default() };
+        let data = b"pad me out to a whole block".repeat(30);
+
+        let archive = compress_with_options(&data, &options);
+
+        assert_eq!(archive.len() % 512, 0);
+        assert_eq!(decompress(&archive).unwrap(), data);
+
+        let both = [archive.clone(), archive].concat();
+        assert_eq!(decompress_all(&both).unwrap(), vec![data.clone(), data]);
+    }
+
+    #[test]
+    fn compress_reports_mode() {
+        let (_, mode) = compress(&noise(4096));
+        assert_eq!(mode, CompressionMode::Stored);
+
+        let (archive, mode) = compress(&b"repeat ".repeat(500));
+        let CompressionMode::Huffman { ratio } = mode else {
+            panic!("expected Huffman mode, got {:?}", mode);
+        };
+        assert!(ratio < 1.0);
+        assert_eq!(ratio, archive.len() as f64 / 3500.0);
+    }
+
+    #[test]
+    fn codec_archive_roundtrip() {
+        for input in [&b""[..], b"x", b"codec owned archive", &noise(300)] {
+            let huffman = Huffman::from_bytes(input.to_vec());
+            let archive = huffman.to_bytes(input).unwrap();
+
+            assert_eq!(Huffman::archive_to_data(&archive).unwrap(), input);
+        }
+
+        let huffman = Huffman::from_bytes(b"abc".to_vec());
+        assert_eq!(huffman.to_bytes(b"abd"), Err(HuffmanError::UnexpectedSymbol(b'd')));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_compression_matches_compress_file() {
+        let dir = std::env::temp_dir().join(format!("huffman-mmap-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.bin");
+
+        // Power-of-two counts never tie, so both paths build the same tree
+        let data: Vec<u8> = (0..10u8).flat_map(|byte| vec![byte; 1 << byte]).collect();
+        fs::write(&input, &data).unwrap();
+
+        let mapped = dir.join("mapped.huff");
+        compress_file_mmap(&input, &mapped).unwrap();
+        let standard = compress_file(&input, false).unwrap();
+
+        assert_eq!(fs::read(&mapped).unwrap(), fs::read(&standard).unwrap());
+        assert_eq!(decompress(&fs::read(&mapped).unwrap()).unwrap(), data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn streamed_archive_matches_to_bytes() {
+        for input in [&b""[..], b"q", &b"stream the archive out in chunks ".repeat(600)] {
+            let huffman = Huffman::from_bytes(input.to_vec());
+            let mut written = Vec::new();
+            huffman.write_archive(input, &mut written).unwrap();
+
+            assert_eq!(written, huffman.to_bytes(input).unwrap());
+        }
+
+        let huffman = Huffman::from_bytes(b"abc".to_vec());
+        let err = huffman.write_archive(b"abcz", &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn stored_symbol_count_catches_corruption() {
+        let options = CompressionOptions { store_symbol_count: true, ..Default::default() };
+        // a=1, b=01, c=00: every bit string decodes, so a flipped bit
+        // changes the output without an invalid or incomplete code
+        let data = b"aaaaaaaabbc";
+        let mut archive = compress_with_options(data, &options);
+        assert_eq!(decompress(&archive).unwrap(), data);
+
+        let payload_start = archive.len() - 14usize.div_ceil(8);
+        archive[payload_start] ^= 1;
+
+        assert_eq!(
+            decompress(&archive),
+            Err(HuffmanError::SymbolCountMismatch { expected: 11, actual: 10 })
+        );
+    }
+
+    #[test]
+    fn hex_roundtrip() {
+        let input = b"hex \x00\xff keeps everything as text".repeat(3);
+        let hex = compress_to_hex(&input);
+
+        assert!(hex.bytes().all(|c| c.is_ascii_digit() || (b'a'..=b'f').contains(&c)));
+        assert_eq!(decompress_from_hex(&hex).unwrap(), input);
+        assert_eq!(decompress_from_hex(&hex.to_uppercase()).unwrap(), input);
+        assert_eq!(decompress_from_hex("48a"), Err(HuffmanError::InvalidHex));
+        assert_eq!(decompress_from_hex("zz"), Err(HuffmanError::InvalidHex));
+        assert_eq!(decompress_from_hex("+a"), Err(HuffmanError::InvalidHex));
+    }
+
+    #[test]
+    fn file_region_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("huffman-region-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("whole.txt");
+        let output = dir.join("region.huff");
+
+        let data: Vec<u8> = (0..2000u32).map(|i| b"region text "[i as usize % 12]).collect();
+        fs::write(&input, &data).unwrap();
+
+        compress_file_region(&input, &output, 700, 500).unwrap();
+        assert_eq!(decompress(&fs::read(&output).unwrap()).unwrap(), &data[700..1200]);
+
+        let err = compress_file_region(&input, &output, 1900, 500).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn decompress_file_restores_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("huffman-perms-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("script.sh");
+        fs::write(&original, b"#!/bin/sh\necho keep my mode\n").unwrap();
+        fs::set_permissions(&original, fs::Permissions::from_mode(0o751)).unwrap();
+
+        let archive = compress_file(&original, false).unwrap();
+        fs::remove_file(&original).unwrap();
+
+        let restored = decompress_file(&archive, None).unwrap();
+        let mode = fs::metadata(&restored).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o751);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn decompress_file_drops_special_permission_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("huffman-setuid-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("tool.huff");
+        let options = CompressionOptions { permissions: Some(0o4755), ..Default::default() };
+        fs::write(&archive, compress_with_options(b"not really a tool", &options)).unwrap();
+
+        let restored = decompress_file(&archive, None).unwrap();
+        let mode = fs::metadata(&restored).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o755);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decompress_into_other_collections() {
+        /// Collects ASCII bytes straight into a `String`.
+        #[derive(Default)]
+        struct Text(String);
+
+        impl Extend<u8> for Text {
+            fn extend<I: IntoIterator<Item = u8>>(&mut self, bytes: I) {
+                self.0.extend(bytes.into_iter().map(char::from));
+            }
+        }
+
+        let input = "decode into whatever buffer the caller has";
+        for archive in [compress(input.as_bytes()).0, compress_with_mtf(input.as_bytes())] {
+            let text: Text = decompress_into(&archive).unwrap();
+            let bytes: Vec<u8> = decompress_into(&archive).unwrap();
+
+            assert_eq!(text.0, input);
+            assert_eq!(bytes, input.as_bytes());
+        }
+    }
+
+    #[test]
+    fn shared_codec_encodes_packets_without_tables() {
+        let training = b"GET /status HTTP/1.1 status: ok GET /metrics HTTP/1.1".repeat(4);
+        let codec = load_codec(&compress(&training).0).unwrap();
+        let packets: [&[u8]; 4] = [b"GET /status", b"status: ok", b"", b"GET /metrics"];
+
+        for packet in packets {
+            let encoded = codec.encode_packet(packet).unwrap();
+            let bit_count = encoded.bit_count();
+            assert_eq!(bit_count, codec.total_bits(packet).unwrap());
+            assert_eq!(encoded.symbol_count(), packet.len());
+
+            let bytes = encoded.bytes();
+            assert_eq!(bytes.len(), bit_count.div_ceil(8));
+            assert_eq!(codec.decode_packed(bytes, bit_count, Default::default()).unwrap(), packet);
+            assert_eq!(codec.decode_data(&encoded).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn loaded_single_symbol_codec_decodes() {
+        let codec = load_codec(&compress(b"aaaaaaaa").0).unwrap();
+        let bits = codec.encode_bytes(b"aaa").unwrap();
+        let bytes = pack_bits(&bits);
+
+        assert_eq!(codec.decode_packed(&bytes, bits.len(), Default::default()).unwrap(), b"aaa");
+    }
+
+    #[test]
+    fn code_table_bytes_predicts_archive_size() {
+        let training = Huffman::from_bytes(noise(3000));
+
+        for data in [noise(3000), noise(10), Vec::new()] {
+            let predicted = MAGIC.len() + 2 + training.code_table_bytes() + 8
+                + training.total_bits(&data).unwrap().div_ceil(8);
+
+            assert_eq!(training.to_bytes(&data).unwrap().len(), predicted);
+        }
+    }
+
+    #[test]
+    fn stored_archive_decompresses_borrowed() {
+        let input = noise(2000);
+        let (archive, mode) = compress(&input);
+        assert_eq!(mode, CompressionMode::Stored);
+
+        let data = decompress_borrowed(&archive).unwrap();
+        assert!(matches!(data, Cow::Borrowed(_)));
+        assert!(archive.as_ptr_range().contains(&data.as_ptr()));
+        assert_eq!(data, &input[..]);
+
+        let text = b"text compresses, so it has to be decoded".repeat(4);
+        let archive = compress(&text).0;
+        let data = decompress_borrowed(&archive).unwrap();
+        assert!(matches!(data, Cow::Owned(_)));
+        assert_eq!(data, &text[..]);
+    }
+
+    #[test]
+    fn streaming_checksum_matches_in_memory_checksum() {
+        let options = CompressionOptions { checksum: true, ..Default::default() };
+        let input = b"the footer is computed during the single encode pass".repeat(300);
+
+        let in_memory = compress_with_options(&input, &options);
+        let mut streamed = Vec::new();
+        Huffman::from_bytes(input.clone())
+            .write_archive_with_options(&input, &options, &mut streamed)
+            .unwrap();
+
+        let footer = |archive: &[u8]| archive[archive.len() - 4..].to_vec();
+        assert_eq!(footer(&streamed), footer(&in_memory));
+        assert_eq!(footer(&streamed), crc32(&input).to_le_bytes());
+        assert_eq!(streamed, in_memory);
+        assert_eq!(decompress(&streamed).unwrap(), input);
+    }
+
+    #[test]
+    fn checksum_catches_corruption_in_every_mode() {
+        let options = CompressionOptions { checksum: true, ..Default::default() };
+        let text = b"aaaaaaaabbbbccd".repeat(20);
+        let stored = compress_with_options(&noise(500), &options);
+        let huffman = compress_with_options(&text, &options);
+
+        let mut stream = stored.clone();
+        stream.extend(&huffman);
+        assert_eq!(decompress_all(&stream).unwrap(), vec![noise(500), text]);
+
+        for mut archive in [stored, huffman] {
+            let last = archive.len() - 1;
+            archive[last] ^= 1;
+            assert!(matches!(decompress(&archive), Err(HuffmanError::ChecksumMismatch { .. })));
+        }
+    }
+
+    #[test]
+    fn compress_with_target_reports_achieved_ratio() {
+        let input = noise(4096);
+        let archive_len = compress(&input).0.len();
+
+        let error = compress_with_target(&input, 1.5).unwrap_err();
+        assert_eq!(error, HuffmanError::RatioNotMet { archive_len, input_len: 4096 });
+        let achieved = error.achieved().unwrap();
+        assert_eq!(achieved, 4096.0 / archive_len as f64);
+        assert!(achieved < 1.0);
+
+        let text = b"abababababababab".repeat(64);
+        let archive = compress_with_target(&text, 4.0).unwrap();
+        assert_eq!(decompress(&archive).unwrap(), text);
+    }
+
+    #[test]
+    fn compress_file_deletes_original_only_when_asked() {
+        let dir = std::env::temp_dir().join(format!("huffman-keep-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("data.txt");
+
+        fs::write(&original, b"kept unless told otherwise").unwrap();
+        let archive = compress_file(&original, false).unwrap();
+        assert!(original.exists());
+        fs::remove_file(&archive).unwrap();
+
+        let archive = compress_file(&original, true).unwrap();
+        assert!(!original.exists());
+        let restored = decompress(&fs::read(&archive).unwrap()).unwrap();
+        assert_eq!(restored, b"kept unless told otherwise");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn returned_codec_decodes_archive_payload() {
+        let input = b"keep the codec around instead of rebuilding it".repeat(5);
+        let (archive, codec) = compress_with_codec(&input);
+
+        let (header, header_len) = read_header(&archive).unwrap();
+        let payload = &archive[header_len..];
+        let decoded = codec.decode_packed(payload, header.bit_count, Default::default()).unwrap();
+
+        assert_eq!(decoded, input);
+        assert_eq!(reconstruct_table(&archive).unwrap(), *codec.table());
+        assert_eq!(decompress(&archive).unwrap(), input);
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use bitvec::prelude::*;
+
+use crate::archive::{byte_entropy, read_table, write_table, ByteReader, PayloadDecoder};
+use crate::huffman::{canonical_codes, Huffman, HuffmanError};
+use crate::io::pack_bits;
+
+
+pub const BLOCK_MAGIC: [u8; 4] = *b"HUFB";
+pub const BLOCK_VERSION: u8 = 1;
+
+/// Window size used by `compress_adaptive_blocks` to track the local
+/// byte distribution; also the smallest block it will cut.
+const ADAPTIVE_WINDOW: usize = 512;
+/// Change in window entropy (bits per byte) treated as a new distribution.
+const ADAPTIVE_ENTROPY_SHIFT: f64 = 1.0;
+
+/// How a single block is stored. Each block picks whichever mode gives
+/// the smallest output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMode {
+    /// Raw bytes, for data that doesn't compress.
+    Stored,
+    /// Coded with the built-in `fixed_table`, so no table is written.
+    Fixed,
+    /// Coded with a table built from the block and written before it.
+    Dynamic,
+}
+
+impl BlockMode {
+    fn selector(self) -> u8 {
+        match self {
+            BlockMode::Stored => 0,
+            BlockMode::Fixed => 1,
+            BlockMode::Dynamic => 2,
+        }
+    }
+
+    fn from_selector(selector: u8) -> Result<Self, HuffmanError> {
+        match selector {
+            0 => Ok(BlockMode::Stored),
+            1 => Ok(BlockMode::Fixed),
+            2 => Ok(BlockMode::Dynamic),
+            _ => Err(HuffmanError::InvalidBlockMode(selector)),
+        }
+    }
+}
+
+/// Directory entry describing one block of a block archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlockEntry {
+    pub mode: BlockMode,
+    pub original_len: usize,
+    pub stored_len: usize,
+}
+
+/// Canonical code shared by every `Fixed` block. Text bytes (printable
+/// ASCII, tab, newline, carriage return) get 7-bit codes; the remaining
+/// bytes get 9 or 10 bits, which exactly fills the code space.
+pub fn fixed_table() -> HashMap<u8, BitVec> {
+    let is_text = |byte: u8| {
+        (0x20..0x7f).contains(&byte) || matches!(byte, b'\t' | b'\n' | b'\r')
+    };
+
+    let mut lengths = Vec::with_capacity(256);
+    let mut others = 0;
+    for byte in 0..=255u8 {
+        if is_text(byte) {
+            lengths.push((byte, 7));
+        } else {
+            lengths.push((byte, if others < 82 { 9 } else { 10 }));
+            others += 1;
+        }
+    }
+
+    canonical_codes(&lengths)
+}
+
+/// Splits `data` into `block_size` chunks and compresses each with its
+/// own best mode. Layout: magic, version, u32 block count, the 2-bit mode
+/// selectors packed four per byte, a directory of (u64 original length,
+/// u64 stored length) per block, then the block bodies in order.
+pub fn compress_blocks(data: &[u8], block_size: usize) -> Vec<u8> {
+    write_block_archive(data.chunks(block_size.max(1)))
+}
+
+/// Like `compress_blocks`, but instead of a fixed size, cuts a block
+/// wherever the byte distribution shifts, so each block is homogeneous
+/// (e.g. text followed by binary data becomes two blocks). The entropy of
+/// consecutive windows is compared, and a jump larger than
+/// `ADAPTIVE_ENTROPY_SHIFT` starts a new block.
+pub fn compress_adaptive_blocks(data: &[u8]) -> Vec<u8> {
+    let boundaries = adaptive_boundaries(data);
+
+    let mut start = 0;
+    let mut blocks = Vec::with_capacity(boundaries.len() + 1);
+    for end in boundaries.into_iter().chain([data.len()]) {
+        blocks.push(&data[start..end]);
+        start = end;
+    }
+
+    write_block_archive(blocks.into_iter().filter(|block| !block.is_empty()))
+}
+
+/// Offsets where `compress_adaptive_blocks` starts a new block.
+fn adaptive_boundaries(data: &[u8]) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut previous: Option<f64> = None;
+
+    for (i, window) in data.chunks(ADAPTIVE_WINDOW).enumerate() {
+        // A short final window has a skewed entropy estimate
+        if window.len() < ADAPTIVE_WINDOW {
+            break;
+        }
+
+        let entropy = byte_entropy(window);
+        if previous.is_some_and(|prev| (entropy - prev).abs() > ADAPTIVE_ENTROPY_SHIFT) {
+            boundaries.push(i * ADAPTIVE_WINDOW);
+        }
+        previous = Some(entropy);
+    }
+
+    boundaries
+}
+
+/// Same output as `compress_blocks`, byte for byte, but the blocks are
+/// compressed in parallel on rayon's thread pool.
+#[cfg(feature = "parallel")]
+pub fn compress_blocks_parallel(data: &[u8], block_size: usize) -> Vec<u8> {
+    use rayon::prelude::*;
+
+    let blocks = data
+        .par_chunks(block_size.max(1))
+        .map(|block| {
+            let (mode, body) = encode_block(block);
+            (mode, block.len(), body)
+        })
+        .collect();
+
+    assemble_block_archive(blocks)
+}
+
+fn write_block_archive<'a>(blocks: impl Iterator<Item = &'a [u8]>) -> Vec<u8> {
+    let blocks = blocks
+        .map(|block| {
+            let (mode, body) = encode_block(block);
+            (mode, block.len(), body)
+        })
+        .collect();
+
+    assemble_block_archive(blocks)
+}
+
+/// Writes the header, directory and bodies for blocks that have already
+/// been encoded, as (mode, original length, body).
+fn assemble_block_archive(blocks: Vec<(BlockMode, usize, Vec<u8>)>) -> Vec<u8> {
+    let mut archive = Vec::new();
+    archive.extend_from_slice(&BLOCK_MAGIC);
+    archive.push(BLOCK_VERSION);
+    archive.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+
+    let mut selectors = vec![0u8; blocks.len().div_ceil(4)];
+    for (i, (mode, _, _)) in blocks.iter().enumerate() {
+        selectors[i / 4] |= mode.selector() << ((i % 4) * 2);
+    }
+    archive.extend(selectors);
+
+    for (_, original_len, body) in &blocks {
+        archive.extend_from_slice(&(*original_len as u64).to_le_bytes());
+        archive.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    }
+    for (_, _, body) in blocks {
+        archive.extend(body);
+    }
+
+    archive
+}
+
+pub fn decompress_blocks(archive: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    let (entries, mut offset) = read_directory(archive)?;
+    let mut output = Vec::new();
+
+    for entry in entries {
+        let end = offset.checked_add(entry.stored_len).ok_or(HuffmanError::Truncated)?;
+        let body = archive.get(offset..end).ok_or(HuffmanError::Truncated)?;
+        output.extend(decode_block(&entry, body)?);
+        offset = end;
+    }
+
+    Ok(output)
+}
+
+/// Decompresses only bytes `start..end` of the original data, decoding
+/// just the blocks that overlap the range. Fails with `InvalidRange` if
+/// the range is reversed or runs past the end of the data, or if the
+/// block lengths add up past `usize`.
+pub fn decompress_range(archive: &[u8], start: usize, end: usize)
+    -> Result<Vec<u8>, HuffmanError> {
+    let (entries, mut offset) = read_directory(archive)?;
+    let total = entries
+        .iter()
+        .try_fold(0usize, |total, entry| total.checked_add(entry.original_len))
+        .ok_or(HuffmanError::InvalidRange)?;
+    if start > end || end > total {
+        return Err(HuffmanError::InvalidRange);
+    }
+
+    let mut output = Vec::with_capacity(end - start);
+    let mut block_start = 0;
+
+    for entry in entries {
+        // Can't overflow: the lengths were summed above
+        let block_end = block_start + entry.original_len;
+        let body_end = offset.checked_add(entry.stored_len).ok_or(HuffmanError::Truncated)?;
+
+        if block_end > start && block_start < end {
+            let body = archive.get(offset..body_end).ok_or(HuffmanError::Truncated)?;
+            let block = decode_block(&entry, body)?;
+
+            let from = start.saturating_sub(block_start);
+            let to = end.min(block_end) - block_start;
+            output.extend_from_slice(&block[from..to]);
+        }
+        if block_end >= end {
+            break;
+        }
+
+        offset = body_end;
+        block_start = block_end;
+    }
+
+    Ok(output)
+}
+
+/// Reads a block archive from a `Read + Seek` source (e.g. a `File`)
+/// without loading it: `new` reads only the header and directory, which
+/// sit at the front, then each block body is fetched by seeking straight
+/// to it when asked for.
+pub struct HuffmanArchiveReader<R> {
+    inner: R,
+    entries: Vec<BlockEntry>,
+    /// Absolute offset of each block body in `inner`.
+    offsets: Vec<u64>,
+    /// Offset of each block's first byte in the original data.
+    starts: Vec<usize>,
+    /// Length of the original data.
+    len: usize,
+}
+
+impl<R: Read + Seek> HuffmanArchiveReader<R> {
+    /// Reads the directory from the current position of `inner`, which
+    /// should be the start of the archive. The block count and stored
+    /// lengths are checked against the length of the stream before
+    /// anything is allocated for them: fails with `Truncated` if the stream
+    /// is too short to hold them, or `InvalidRange` if the original lengths
+    /// add up past `usize`.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let base = inner.stream_position()?;
+        let end = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(base))?;
+
+        // Magic, version and block count, which give the directory size
+        let mut directory = vec![0u8; BLOCK_MAGIC.len() + 5];
+        inner.read_exact(&mut directory)?;
+        let count_bytes = directory[BLOCK_MAGIC.len() + 1..].try_into().unwrap();
+        let count = u32::from_le_bytes(count_bytes) as u64;
+
+        let prefix_len = directory.len();
+        let directory_len = count.div_ceil(4) + count * 16;
+        if directory_len > end.saturating_sub(base + prefix_len as u64) {
+            return Err(HuffmanError::Truncated.into());
+        }
+        directory.resize(prefix_len + directory_len as usize, 0);
+        inner.read_exact(&mut directory[prefix_len..])?;
+
+        let (entries, body_start) = read_directory(&directory)?;
+        let mut offsets = Vec::with_capacity(entries.len());
+        let mut starts = Vec::with_capacity(entries.len());
+        let (mut offset, mut start) = (base + body_start as u64, 0usize);
+        for entry in &entries {
+            offsets.push(offset);
+            starts.push(start);
+            offset = offset
+                .checked_add(entry.stored_len as u64)
+                .filter(|body_end| *body_end <= end)
+                .ok_or(HuffmanError::Truncated)?;
+            start = start
+                .checked_add(entry.original_len)
+                .ok_or(HuffmanError::InvalidRange)?;
+        }
+
+        Ok(HuffmanArchiveReader { inner, entries, offsets, starts, len: start })
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Length of the original data.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Seeks to block `index` and decodes it. Fails with `InvalidInput`
+    /// if there is no such block. Its stored length was checked against
+    /// the stream in `new`, so the body buffer is bounded by the stream.
+    pub fn read_block(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "block index out of range")
+        })?;
+
+        let mut body = vec![0u8; entry.stored_len];
+        self.inner.seek(SeekFrom::Start(self.offsets[index]))?;
+        self.inner.read_exact(&mut body)?;
+
+        Ok(decode_block(entry, &body)?)
+    }
+
+    /// Like `decompress_range`, reading and decoding only the blocks that
+    /// overlap `start..end`.
+    pub fn read_range(&mut self, start: usize, end: usize) -> io::Result<Vec<u8>> {
+        if start > end || end > self.len() {
+            return Err(HuffmanError::InvalidRange.into());
+        }
+
+        let mut output = Vec::with_capacity(end - start);
+        for index in 0..self.entries.len() {
+            let block_start = self.starts[index];
+            let block_end = block_start + self.entries[index].original_len;
+            if block_start >= end {
+                break;
+            }
+            if block_end <= start {
+                continue;
+            }
+
+            let block = self.read_block(index)?;
+            let from = start.saturating_sub(block_start);
+            let to = end.min(block_end) - block_start;
+            output.extend_from_slice(&block[from..to]);
+        }
+
+        Ok(output)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Parses the block directory, returning the entries and the offset of
+/// the first block body.
+pub(crate) fn read_directory(archive: &[u8]) -> Result<(Vec<BlockEntry>, usize), HuffmanError> {
+    let mut reader = ByteReader::new(archive);
+
+    if reader.take(BLOCK_MAGIC.len())? != BLOCK_MAGIC {
+        return Err(HuffmanError::InvalidMagic);
+    }
+
+    let version = reader.take_u8()?;
+    if version != BLOCK_VERSION {
+        return Err(HuffmanError::UnsupportedVersion(version));
+    }
+
+    let count = reader.take_u32()? as usize;
+    let selectors = reader.take(count.div_ceil(4))?;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let mode = BlockMode::from_selector((selectors[i / 4] >> ((i % 4) * 2)) & 0b11)?;
+        let original_len = reader.take_u64()? as usize;
+        let stored_len = reader.take_u64()? as usize;
+
+        entries.push(BlockEntry { mode, original_len, stored_len });
+    }
+
+    Ok((entries, reader.pos))
+}
+
+fn encode_block(block: &[u8]) -> (BlockMode, Vec<u8>) {
+    let fixed = pack_bits(&encode_with_table(block, &fixed_table()));
+
+    let mut huffman = Huffman::from_bytes(block.to_vec());
+    let mut dynamic = Vec::new();
+    write_table(&mut dynamic, huffman.table());
+    dynamic.extend(pack_bits(
+        &huffman.encode().expect("table built from the block covers every byte"),
+    ));
+
+    // Ties go to the simpler mode
+    if block.len() <= fixed.len() && block.len() <= dynamic.len() {
+        (BlockMode::Stored, block.to_vec())
+    } else if fixed.len() <= dynamic.len() {
+        (BlockMode::Fixed, fixed)
+    } else {
+        (BlockMode::Dynamic, dynamic)
+    }
+}
+
+pub(crate) fn decode_block(entry: &BlockEntry, body: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    match entry.mode {
+        BlockMode::Stored => {
+            if body.len() != entry.original_len {
+                return Err(HuffmanError::BlockLengthMismatch {
+                    expected: entry.original_len,
+                    actual: body.len(),
+                });
+            }
+            Ok(body.to_vec())
+        }
+        BlockMode::Fixed => {
+            let table = fixed_table()
+                .into_iter()
+                .map(|(byte, code)| (code.iter().by_vals().collect(), byte))
+                .collect();
+            decode_symbols(table, body, entry.original_len)
+        }
+        BlockMode::Dynamic => {
+            let mut reader = ByteReader::new(body);
+            let table = read_table(&mut reader)?;
+            decode_symbols(table, &body[reader.pos..], entry.original_len)
+        }
+    }
+}
+
+/// Decodes exactly `count` symbols; the padding bits after the last one
+/// are ignored.
+fn decode_symbols(table: HashMap<BitVec<u8>, u8>, payload: &[u8], count: usize)
+    -> Result<Vec<u8>, HuffmanError> {
+    let mut decoder = PayloadDecoder::new(table);
+    // `count` comes from the archive; every symbol takes at least one bit
+    let mut output = Vec::with_capacity(count.min(payload.len() * 8));
+
+    for bit in payload.view_bits::<Lsb0>().iter().by_vals() {
+        if output.len() == count {
+            break;
+        }
+        if let Some(byte) = decoder.push_bit(bit)? {
+            output.push(byte);
+        }
+    }
+
+    if output.len() != count {
+        return Err(HuffmanError::Truncated);
+    }
+
+    Ok(output)
+}
+
+fn encode_with_table(data: &[u8], table: &HashMap<u8, BitVec>) -> BitVec {
+    let mut bits = bitvec![];
+    for byte in data {
+        bits.extend(table[byte].iter());
+    }
+
+    bits
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random bytes (xorshift64).
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_table_is_complete_and_prefix_free() {
+        let table = fixed_table();
+        assert_eq!(table.len(), 256);
+
+        let kraft: f64 = table.values().map(|code| 0.5f64.powi(code.len() as i32)).sum();
+        assert_eq!(kraft, 1.0);
+
+        for (a, code_a) in &table {
+            for (b, code_b) in &table {
+                if a != b {
+                    assert!(!code_b.starts_with(code_a));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn each_block_picks_its_smallest_mode() {
+        let block_size = 256;
+        let mut data = noise(block_size);
+        data.extend(b"ab".repeat(block_size / 2));
+        data.extend(b"Short plain text, no repeats!");
+
+        let archive = compress_blocks(&data, block_size);
+        let (entries, _) = read_directory(&archive).unwrap();
+
+        let modes: Vec<BlockMode> = entries.iter().map(|entry| entry.mode).collect();
+        assert_eq!(modes, vec![BlockMode::Stored, BlockMode::Dynamic, BlockMode::Fixed]);
+        assert_eq!(decompress_blocks(&archive).unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_oversized_block_length() {
+        let archive = compress_blocks(&b"ab".repeat(200), 1024);
+        let (entries, _) = read_directory(&archive).unwrap();
+        assert_eq!(entries[0].mode, BlockMode::Dynamic);
+
+        // The first entry's original length follows the magic, version,
+        // block count and one selector byte
+        let at = BLOCK_MAGIC.len() + 1 + 4 + 1;
+        let mut huge = archive.clone();
+        huge[at..at + 8].copy_from_slice(&(1u64 << 42).to_le_bytes());
+        assert_eq!(decompress_blocks(&huge), Err(HuffmanError::Truncated));
+
+        let mut overflowing = archive;
+        overflowing[at + 8..at + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(decompress_blocks(&overflowing), Err(HuffmanError::Truncated));
+    }
+
+    #[test]
+    fn stored_block_must_match_directory_length() {
+        let entry = BlockEntry { mode: BlockMode::Stored, original_len: 4, stored_len: 3 };
+
+        assert_eq!(
+            decode_block(&entry, b"abc"),
+            Err(HuffmanError::BlockLengthMismatch { expected: 4, actual: 3 })
+        );
+    }
+
+    #[test]
+    fn single_symbol_block_roundtrip() {
+        let data = vec![b'z'; 1000];
+        let archive = compress_blocks(&data, 300);
+
+        assert_eq!(decompress_blocks(&archive).unwrap(), data);
+    }
+
+    #[test]
+    fn empty_block_archive_roundtrip() {
+        let archive = compress_blocks(b"", 64);
+
+        assert_eq!(decompress_blocks(&archive).unwrap(), b"");
+    }
+
+    #[test]
+    fn adaptive_blocks_cut_at_distribution_shift() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(100);
+        let transition = text.len();
+        let data = [text, noise(4000)].concat();
+
+        let archive = compress_adaptive_blocks(&data);
+        let (entries, _) = read_directory(&archive).unwrap();
+
+        assert!(entries.len() >= 2);
+        let first_len = entries[0].original_len;
+        assert!(first_len.abs_diff(transition) <= ADAPTIVE_WINDOW);
+        assert_eq!(decompress_blocks(&archive).unwrap(), data);
+    }
+
+    #[test]
+    fn range_decodes_only_overlapping_blocks() {
+        let data = [b"plain text block ".repeat(40), noise(700), b"xyxyxy".repeat(100)].concat();
+        let archive = compress_blocks(&data, 256);
+
+        assert_eq!(decompress_range(&archive, 300, 1100).unwrap(), &data[300..1100]);
+        assert_eq!(decompress_range(&archive, 0, data.len()).unwrap(), data);
+        assert_eq!(decompress_range(&archive, 512, 512).unwrap(), b"");
+        assert_eq!(decompress_range(&archive, 10, data.len() + 1), Err(HuffmanError::InvalidRange));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_blocks_match_serial_output() {
+        let data = [b"parallel text block ".repeat(60), noise(900), b"aab".repeat(300)].concat();
+
+        assert_eq!(compress_blocks_parallel(&data, 200), compress_blocks(&data, 200));
+    }
+
+    /// Records the byte ranges read through it.
+    struct ReadLog<R> {
+        inner: R,
+        reads: Vec<std::ops::Range<u64>>,
+    }
+
+    impl<R: Read + Seek> Read for ReadLog<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.inner.stream_position()?;
+            let len = self.inner.read(buf)?;
+            self.reads.push(start..start + len as u64);
+            Ok(len)
+        }
+    }
+
+    impl<R: Seek> Seek for ReadLog<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn range_rejects_overflowing_block_lengths() {
+        let archive = compress_blocks(&b"ab".repeat(200), 128);
+        let (entries, _) = read_directory(&archive).unwrap();
+        assert!(entries.len() >= 2);
+
+        // Original lengths of the first two blocks, after the selectors
+        let first_at = BLOCK_MAGIC.len() + 1 + 4 + entries.len().div_ceil(4);
+        let mut overflowing = archive;
+        for at in [first_at, first_at + 16] {
+            overflowing[at..at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        }
+
+        assert_eq!(decompress_range(&overflowing, 0, 10), Err(HuffmanError::InvalidRange));
+    }
+
+    #[test]
+    fn archive_reader_checks_lengths_against_stream() {
+        let archive = compress_blocks(&b"ab".repeat(200), 1024);
+        let count_at = BLOCK_MAGIC.len() + 1;
+        let kind = |archive: Vec<u8>| {
+            HuffmanArchiveReader::new(io::Cursor::new(archive)).err().map(|err| err.kind())
+        };
+
+        let mut many_blocks = archive.clone();
+        many_blocks[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(kind(many_blocks), Some(io::ErrorKind::InvalidData));
+
+        // Stored length of the only block, after its original length
+        let stored_at = count_at + 4 + 1 + 8;
+        let mut long_body = archive.clone();
+        long_body[stored_at..stored_at + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert_eq!(kind(long_body), Some(io::ErrorKind::InvalidData));
+
+        assert_eq!(kind(archive), None);
+    }
+
+    #[test]
+    fn archive_reader_seeks_to_late_block() {
+        let data = [b"early text block ".repeat(60), noise(600), b"late, ".repeat(80)].concat();
+        let archive = compress_blocks(&data, 256);
+        let path = std::env::temp_dir().join(format!("huffman-blocks-{}", std::process::id()));
+        std::fs::write(&path, &archive).unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mut reader = HuffmanArchiveReader::new(ReadLog { inner: file, reads: Vec::new() })
+            .unwrap();
+        let last = reader.block_count() - 1;
+        let late = reader.read_block(last).unwrap();
+        let range = reader.read_range(data.len() - 40, data.len()).unwrap();
+        let log = reader.into_inner();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(late, &data[last * 256..]);
+        assert_eq!(range, &data[data.len() - 40..]);
+
+        // Nothing between the directory and the last block was touched
+        let (_, body_start) = read_directory(&archive).unwrap();
+        let last_body = HuffmanArchiveReader::new(io::Cursor::new(&archive)).unwrap().offsets[last];
+        for read in &log.reads {
+            assert!(read.end <= body_start as u64 || read.start >= last_body, "{:?}", read);
+        }
+    }
+}
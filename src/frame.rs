@@ -0,0 +1,85 @@
+use std::io::{self, Read, Write};
+
+use bitvec::prelude::*;
+
+use crate::huffman::Huffman;
+use crate::io::pack_bits;
+
+
+/// Writes `message` as one frame coded with a codec both ends share, so
+/// no table is sent: a u32 little-endian bit count, then the packed
+/// bits. Fails with `InvalidInput` if the codec has no code for a byte.
+pub fn write_frame<W: Write>(writer: &mut W, codec: &Huffman, message: &[u8]) -> io::Result<()> {
+    let bits = codec.encode_bytes(message)?;
+    let bit_count = u32::try_from(bits.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+
+    writer.write_all(&bit_count.to_le_bytes())?;
+    writer.write_all(&pack_bits(&bits))
+}
+
+/// Reads one frame written by `write_frame` with the same codec. The
+/// payload buffer grows as bytes arrive rather than being sized from the
+/// untrusted bit count, so a bogus count fails with `UnexpectedEof`
+/// instead of allocating up to 512 MiB.
+pub fn read_frame<R: Read>(reader: &mut R, codec: &Huffman) -> io::Result<Vec<u8>> {
+    let mut count_bytes = [0u8; 4];
+    reader.read_exact(&mut count_bytes)?;
+    let bit_count = u32::from_le_bytes(count_bytes) as usize;
+
+    let payload_len = bit_count.div_ceil(8);
+    let mut payload = Vec::new();
+    reader.take(payload_len as u64).read_to_end(&mut payload)?;
+    if payload.len() < payload_len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "frame is truncated"));
+    }
+
+    let bits = &payload.view_bits::<Lsb0>()[..bit_count];
+    Ok(codec.decode_checked(bits)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn frames_over_a_pipe() {
+        let codec = Huffman::from_bytes(b"hello there, how are you? fine thanks".to_vec());
+        let messages: Vec<&[u8]> = vec![b"hello", b"how are you?", b"", b"fine thanks"];
+
+        let (mut reader, mut writer) = io::pipe().unwrap();
+        let received = thread::scope(|scope| {
+            scope.spawn(|| {
+                for message in &messages {
+                    write_frame(&mut writer, &codec, message).unwrap();
+                }
+            });
+
+            (0..messages.len())
+                .map(|_| read_frame(&mut reader, &codec).unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(received, messages);
+    }
+
+    #[test]
+    fn unknown_byte_is_rejected() {
+        let codec = Huffman::from_bytes(b"abc".to_vec());
+        let err = write_frame(&mut Vec::new(), &codec, b"abz").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn oversized_bit_count_is_truncated() {
+        let codec = Huffman::from_bytes(b"abc".to_vec());
+        let mut frame = u32::MAX.to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0xff; 16]);
+
+        let err = read_frame(&mut &frame[..], &codec).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
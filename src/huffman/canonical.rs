@@ -0,0 +1,152 @@
+use bitvec::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+
+use super::HuffmanError;
+
+
+/// Assigns canonical codes from `(symbol, code length)` pairs, for byte
+/// or any other ordered symbols (e.g. `char`s in text mode): symbols are
+/// ordered by length then value and given consecutive codes, each one the
+/// previous code plus one, extended with zeros when the length grows.
+/// Codes are built as bit vectors since lengths can exceed 64 bits.
+///
+/// The lengths are assumed to satisfy the Kraft inequality.
+pub fn canonical_codes<T: Copy + Ord + Hash>(lengths: &[(T, usize)]) -> HashMap<T, BitVec> {
+    let mut sorted = lengths.to_vec();
+    sorted.sort_by_key(|(byte, len)| (*len, *byte));
+
+    let mut table = HashMap::new();
+    let mut code: BitVec = bitvec![];
+
+    for (i, (byte, len)) in sorted.into_iter().enumerate() {
+        if i > 0 {
+            increment(&mut code);
+        }
+        code.resize(len, false);
+
+        table.insert(byte, code.clone());
+    }
+
+    table
+}
+
+/// Checks that `(symbol, code length)` pairs describe a complete prefix
+/// code: lengths are non-zero, symbols unique, and the Kraft sum exactly
+/// 1, so every bit sequence decodes. A lone symbol with a 1-bit code and
+/// the empty set are allowed too. Fails with `InvalidCodeLengths`.
+pub(crate) fn check_code_lengths<T: Copy + Eq + Hash>(lengths: &[(T, usize)])
+    -> Result<(), HuffmanError> {
+    let max_len = lengths.iter().map(|(_, len)| *len).max().unwrap_or(0);
+    let mut seen = HashSet::with_capacity(lengths.len());
+    let mut per_length = vec![0usize; max_len + 1];
+    for (symbol, len) in lengths {
+        if *len == 0 || !seen.insert(*symbol) {
+            return Err(HuffmanError::InvalidCodeLengths);
+        }
+        per_length[*len] += 1;
+    }
+
+    // Walk down the levels tracking how many codes are still free; it
+    // only goes negative for an over-subscribed set. With more free codes
+    // than symbols nothing can run out, so cap it there to avoid overflow.
+    let mut available = 1usize;
+    for used in &per_length[1..] {
+        available = (available * 2).min(lengths.len() + 1);
+        available = available
+            .checked_sub(*used)
+            .ok_or(HuffmanError::InvalidCodeLengths)?;
+    }
+
+    // Codes left over would be dead ends in the tree that decoding
+    // refuses (see `HuffmanNode::is_complete`)
+    let single = matches!(lengths, [(_, 1)]);
+    if available > 0 && !lengths.is_empty() && !single {
+        return Err(HuffmanError::InvalidCodeLengths);
+    }
+
+    Ok(())
+}
+
+/// Huffman code lengths for `counts`, sorted by symbol, for symbols that
+/// don't fit a byte tree (e.g. `char`s in text mode). Equal counts are
+/// merged in symbol order, so the result is reproducible. A lone symbol
+/// still gets a 1-bit code.
+pub(crate) fn code_lengths<T: Copy + Ord>(counts: &HashMap<T, usize>) -> Vec<(T, usize)> {
+    let mut symbols: Vec<(T, usize)> = counts.iter().map(|(c, count)| (*c, *count)).collect();
+    symbols.sort_unstable();
+
+    // Nodes 0..n are the symbols, merges are appended after them
+    let mut parents = vec![usize::MAX; symbols.len()];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = symbols
+        .iter()
+        .enumerate()
+        .map(|(node, (_, count))| Reverse((*count, node)))
+        .collect();
+
+    while heap.len() > 1 {
+        let Reverse((left_count, left)) = heap.pop().unwrap();
+        let Reverse((right_count, right)) = heap.pop().unwrap();
+
+        let parent = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = parent;
+        parents[right] = parent;
+        heap.push(Reverse((left_count + right_count, parent)));
+    }
+
+    symbols
+        .iter()
+        .enumerate()
+        .map(|(node, (symbol, _))| {
+            let mut depth = 0;
+            let mut node = node;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            (*symbol, depth.max(1))
+        })
+        .collect()
+}
+
+/// Adds one to `code` read as a big-endian binary number.
+fn increment(code: &mut BitVec) {
+    for i in (0..code.len()).rev() {
+        if code[i] {
+            code.set(i, false);
+        } else {
+            code.set(i, true);
+            return;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assigns_codes_in_length_then_symbol_order() {
+        let table = canonical_codes(&[(b'c', 2), (b'a', 1), (b'd', 3), (b'b', 3)]);
+
+        assert_eq!(table[&b'a'], bitvec![0]);
+        assert_eq!(table[&b'c'], bitvec![1, 0]);
+        assert_eq!(table[&b'b'], bitvec![1, 1, 0]);
+        assert_eq!(table[&b'd'], bitvec![1, 1, 1]);
+    }
+
+    #[test]
+    fn built_lengths_pass_the_check() {
+        let counts = HashMap::from([('a', 5), ('é', 2), ('🎉', 1), ('z', 1)]);
+        let lengths = code_lengths(&counts);
+
+        assert_eq!(lengths, [('a', 1), ('z', 3), ('é', 2), ('🎉', 3)]);
+        assert_eq!(check_code_lengths(&lengths), Ok(()));
+        let invalid = Err(HuffmanError::InvalidCodeLengths);
+        assert_eq!(check_code_lengths(&[('a', 1), ('a', 1)]), invalid);
+        assert_eq!(check_code_lengths(&[('a', 1), ('b', 2)]), invalid);
+    }
+}
@@ -0,0 +1,120 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::huffman_node::HuffmanNode;
+
+
+/// Strategy for turning byte frequencies into a code tree. Left edges
+/// are `0` bits and right edges `1` bits, as in `Huffman::make_table`.
+pub trait CodeBuilder {
+    fn build(&self, counts: HashMap<u8, usize>) -> HuffmanNode;
+}
+
+/// Optimal prefix codes: repeatedly merge the two least frequent nodes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StandardHuffman;
+
+impl CodeBuilder for StandardHuffman {
+    fn build(&self, counts: HashMap<u8, usize>) -> HuffmanNode {
+        let mut min_heap = HuffmanNode::build_min_heap(counts);
+        HuffmanNode::build_tree(&mut min_heap)
+    }
+}
+
+/// Standard Huffman, but when counts tie, the nodes with the shortest
+/// subtrees are merged first (then lower bytes, for leaves). The codes
+/// are just as short on average, and the tree is kept as balanced as the
+/// counts allow, which can lower the maximum code length on uniform-ish
+/// data.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BalancedHuffman;
+
+impl CodeBuilder for BalancedHuffman {
+    fn build(&self, counts: HashMap<u8, usize>) -> HuffmanNode {
+        let mut symbols: Vec<(u8, usize)> = counts.into_iter().collect();
+        symbols.sort_unstable();
+
+        // Heap entries are (count, height, index into `nodes`); the index
+        // makes the order total, so ties resolve the same way every run
+        let mut nodes: Vec<Option<HuffmanNode>> = Vec::with_capacity(symbols.len() * 2);
+        let mut heap = BinaryHeap::with_capacity(symbols.len());
+        for (byte, count) in symbols {
+            heap.push(Reverse((count, 0, nodes.len())));
+            nodes.push(Some(HuffmanNode { byte: Some(byte), count, left: None, right: None }));
+        }
+
+        while heap.len() > 1 {
+            let Reverse((left_count, left_height, left)) = heap.pop().unwrap();
+            let Reverse((right_count, right_height, right)) = heap.pop().unwrap();
+
+            let parent = HuffmanNode {
+                byte: None,
+                count: left_count + right_count,
+                left: nodes[left].take().map(Box::new),
+                right: nodes[right].take().map(Box::new),
+            };
+            heap.push(Reverse((parent.count, left_height.max(right_height) + 1, nodes.len())));
+            nodes.push(Some(parent));
+        }
+
+        match heap.pop() {
+            Some(Reverse((_, _, root))) => nodes[root].take().unwrap(),
+            None => HuffmanNode::default(),
+        }
+    }
+}
+
+/// Top-down Shannon-Fano codes: sort symbols by frequency and recursively
+/// split them into two groups of as close to equal weight as possible.
+/// Never better than Huffman, sometimes worse.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShannonFano;
+
+impl CodeBuilder for ShannonFano {
+    fn build(&self, counts: HashMap<u8, usize>) -> HuffmanNode {
+        let mut symbols: Vec<(u8, usize)> = counts.into_iter().collect();
+        symbols.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        if symbols.is_empty() {
+            return HuffmanNode::default();
+        }
+
+        Self::split(&symbols)
+    }
+}
+
+impl ShannonFano {
+    fn split(symbols: &[(u8, usize)]) -> HuffmanNode {
+        let total: usize = symbols.iter().map(|symbol| symbol.1).sum();
+
+        if let [(byte, count)] = symbols {
+            return HuffmanNode {
+                byte: Some(*byte),
+                count: *count,
+                left: None,
+                right: None,
+            };
+        }
+
+        // Cut where the running total gets closest to half, keeping at
+        // least one symbol on each side
+        let mut running = 0;
+        let mut cut = 1;
+        let mut best_diff = usize::MAX;
+        for (i, symbol) in symbols[..symbols.len() - 1].iter().enumerate() {
+            running += symbol.1;
+            let diff = running.abs_diff(total - running);
+            if diff < best_diff {
+                best_diff = diff;
+                cut = i + 1;
+            }
+        }
+
+        HuffmanNode {
+            byte: None,
+            count: total,
+            left: Some(Box::new(Self::split(&symbols[..cut]))),
+            right: Some(Box::new(Self::split(&symbols[cut..]))),
+        }
+    }
+}
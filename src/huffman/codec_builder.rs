@@ -0,0 +1,171 @@
+use std::mem::size_of;
+
+use bitvec::prelude::*;
+
+use super::{Huffman, HuffmanError, HuffmanNode, StandardHuffman};
+
+
+/// Builder for a `Huffman` codec with optional constraints on the input.
+#[derive(Debug, Default, Clone)]
+pub struct CodecBuilder {
+    alphabet: Option<Vec<u8>>,
+    reserved: Vec<u8>,
+    max_memory: Option<usize>,
+}
+
+impl CodecBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the input to `alphabet` (e.g. `b"ACGT"` for DNA). `build`
+    /// rejects any other byte, and every alphabet symbol gets a code even
+    /// if the training input never uses it, so the codec can encode any
+    /// data over the alphabet.
+    pub fn restrict_alphabet(mut self, alphabet: &[u8]) -> Self {
+        self.alphabet = Some(alphabet.to_vec());
+        self
+    }
+
+    /// Never gives `bytes` codes of their own: each is written as one
+    /// shared escape code followed by the byte as an 8-bit literal (most
+    /// significant bit first), e.g. for marker bytes an outer format must
+    /// be able to find. The escape code is the leaf of the first reserved
+    /// byte, weighted by how often all reserved bytes occur. Archives
+    /// don't record reserved bytes, so decode with the codec itself.
+    pub fn reserve(mut self, bytes: &[u8]) -> Self {
+        self.reserved = bytes.to_vec();
+        self
+    }
+
+    /// Fails `build` with `MemoryLimitExceeded` before anything is built
+    /// if the tree and code table are estimated to need more than `bytes`.
+    /// The estimate assumes the worst case of a fully skewed tree, where
+    /// codes grow to one bit less than the alphabet size.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    pub fn build(self, input: Vec<u8>) -> Result<Huffman, HuffmanError> {
+        let mut counts = HuffmanNode::count_bytes(&input);
+
+        if let Some(alphabet) = &self.alphabet {
+            if let Some(byte) = input.iter().find(|byte| !alphabet.contains(byte)) {
+                return Err(HuffmanError::UnexpectedSymbol(*byte));
+            }
+
+            for byte in alphabet {
+                counts.entry(*byte).or_insert(0);
+            }
+        }
+
+        if let Some(escape) = self.reserved.first() {
+            let escaped: usize = self.reserved.iter().filter_map(|byte| counts.remove(byte)).sum();
+            // Kept even if unused, so later data can still contain them
+            counts.insert(*escape, escaped.max(1));
+        }
+
+        if let Some(limit) = self.max_memory {
+            let estimated = estimate_memory(counts.len());
+            if estimated > limit {
+                return Err(HuffmanError::MemoryLimitExceeded { estimated, limit });
+            }
+        }
+
+        // Unused alphabet symbols keep their zero counts (and so a code),
+        // which `from_counts` would drop
+        let mut huffman = Huffman::build(input, counts, &StandardHuffman);
+        huffman.reserved = self.reserved;
+
+        Ok(huffman)
+    }
+}
+
+/// Upper bound on the bytes a codec over `symbols` symbols needs for its
+/// tree (2n - 1 nodes) and table (an entry and a code buffer per symbol).
+fn estimate_memory(symbols: usize) -> usize {
+    let nodes = (2 * symbols).saturating_sub(1) * size_of::<HuffmanNode>();
+    let longest_code = symbols.saturating_sub(1).max(1);
+    let code_buffer = longest_code.div_ceil(usize::BITS as usize) * size_of::<usize>();
+    let entries = symbols * (size_of::<(u8, BitVec)>() + code_buffer);
+
+    nodes + entries
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restricted_alphabet_roundtrip() {
+        let mut huffman = CodecBuilder::new()
+            .restrict_alphabet(b"ACGT")
+            .build(b"ACGTACGT".to_vec())
+            .unwrap();
+
+        assert_eq!(huffman.table().len(), 4);
+        assert!(huffman.table().values().all(|code| code.len() == 2));
+
+        let encoded = huffman.encode().unwrap();
+        assert_eq!(huffman.decode(&encoded).unwrap(), "ACGTACGT");
+    }
+
+    #[test]
+    fn restricted_alphabet_covers_unused_symbols() {
+        let huffman = CodecBuilder::new()
+            .restrict_alphabet(b"ACGT")
+            .build(b"AAAC".to_vec())
+            .unwrap();
+
+        assert!(huffman.encode_bytes(b"GATTACA").is_ok());
+    }
+
+    #[test]
+    fn restricted_alphabet_rejects_other_bytes() {
+        let result = CodecBuilder::new()
+            .restrict_alphabet(b"ACGT")
+            .build(b"ACGTNACGT".to_vec());
+
+        assert_eq!(result.unwrap_err(), HuffmanError::UnexpectedSymbol(b'N'));
+    }
+
+    #[test]
+    fn reserved_bytes_are_escaped() {
+        let input = b"ab\xffcab\xfe\xffaaab".to_vec();
+        let mut huffman = CodecBuilder::new().reserve(&[0xff, 0xfe]).build(input.clone()).unwrap();
+
+        let escape = huffman.table()[&0xff].clone();
+        assert!(!huffman.table().contains_key(&0xfe));
+
+        let encoded = huffman.encode().unwrap();
+        let mut expected = bitvec![];
+        for byte in &input {
+            if [0xff, 0xfe].contains(byte) {
+                expected.extend(escape.iter());
+                expected.extend((0..8).rev().map(|i| byte >> i & 1 == 1));
+            } else {
+                expected.extend(huffman.table()[byte].iter());
+            }
+        }
+        assert_eq!(encoded, expected);
+
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), input);
+        assert_eq!(huffman.decode_lut(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn memory_budget_is_checked_before_building() {
+        let input: Vec<u8> = (0..=255).collect();
+
+        let tiny = CodecBuilder::new().max_memory(1024).build(input.clone());
+        assert!(matches!(
+            tiny,
+            Err(HuffmanError::MemoryLimitExceeded { estimated, limit: 1024 }) if estimated > 1024
+        ));
+
+        let huffman = CodecBuilder::new().max_memory(1 << 20).build(input).unwrap();
+        assert_eq!(huffman.table().len(), 256);
+    }
+}
@@ -0,0 +1,78 @@
+use bitvec::prelude::*;
+
+use super::Huffman;
+
+
+/// One difference between two codecs' code tables, from `Huffman::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolDiff {
+    /// The symbol has a code in `self` but not in `other`.
+    OnlyInSelf(u8),
+    /// The symbol has a code in `other` but not in `self`.
+    OnlyInOther(u8),
+    /// The symbol's code is a different length in each codec.
+    LengthDiffers { symbol: u8, self_len: usize, other_len: usize },
+    /// Same length, but different bits.
+    CodeDiffers { symbol: u8, self_code: BitVec, other_code: BitVec },
+}
+
+impl Huffman {
+    /// Compares this codec's table with `other`'s, one entry per symbol
+    /// that differs, in symbol order. Handy for seeing why two codecs
+    /// trained on similar data diverged.
+    pub fn diff(&self, other: &Huffman) -> Vec<SymbolDiff> {
+        let mut symbols: Vec<u8> = self.table.keys().chain(other.table.keys()).copied().collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+
+        symbols
+            .into_iter()
+            .filter_map(|symbol| match (self.table.get(&symbol), other.table.get(&symbol)) {
+                (Some(_), None) => Some(SymbolDiff::OnlyInSelf(symbol)),
+                (None, Some(_)) => Some(SymbolDiff::OnlyInOther(symbol)),
+                (Some(a), Some(b)) if a.len() != b.len() => Some(SymbolDiff::LengthDiffers {
+                    symbol,
+                    self_len: a.len(),
+                    other_len: b.len(),
+                }),
+                (Some(a), Some(b)) if a != b => Some(SymbolDiff::CodeDiffers {
+                    symbol,
+                    self_code: a.clone(),
+                    other_code: b.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_swapped_codes() {
+        let left = Huffman::from_bytes(b"aaab".to_vec());
+        let right = Huffman::from_bytes(b"abbb".to_vec());
+
+        assert_eq!(left.diff(&right), vec![
+            SymbolDiff::CodeDiffers { symbol: b'a', self_code: bitvec![1], other_code: bitvec![0] },
+            SymbolDiff::CodeDiffers { symbol: b'b', self_code: bitvec![0], other_code: bitvec![1] },
+        ]);
+        assert!(left.diff(&left).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_missing_symbols_and_lengths() {
+        let left = Huffman::from_bytes(b"aaaabbc".to_vec());
+        let right = Huffman::from_bytes(b"aaaabbbbbd".to_vec());
+
+        assert_eq!(left.diff(&right), vec![
+            SymbolDiff::LengthDiffers { symbol: b'a', self_len: 1, other_len: 2 },
+            SymbolDiff::LengthDiffers { symbol: b'b', self_len: 2, other_len: 1 },
+            SymbolDiff::OnlyInSelf(b'c'),
+            SymbolDiff::OnlyInOther(b'd'),
+        ]);
+    }
+}
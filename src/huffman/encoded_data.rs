@@ -0,0 +1,127 @@
+use bitvec::prelude::*;
+
+use super::{Huffman, HuffmanError};
+
+
+/// Encoded bits together with what is needed to decode them: how many
+/// bits are used and how many symbols they hold. Produced by
+/// `Huffman::encode_packet` and consumed by `Huffman::decode_data`, so the
+/// counts can't get separated from (or out of step with) the bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedData {
+    bits: BitVec<u8>,
+    symbol_count: usize,
+}
+
+impl EncodedData {
+    /// Rebuilds encoded data from its packed bytes (LSB-first, as `bytes`
+    /// returns) and counts, e.g. after storing them. Fails with
+    /// `Truncated` if `bytes` holds fewer than `bit_count` bits.
+    pub fn from_parts(bytes: Vec<u8>, bit_count: usize, symbol_count: usize)
+        -> Result<Self, HuffmanError> {
+        if bit_count > bytes.len() * 8 {
+            return Err(HuffmanError::Truncated);
+        }
+
+        let mut bits = BitVec::from_vec(bytes);
+        bits.truncate(bit_count);
+
+        Ok(EncodedData { bits, symbol_count })
+    }
+
+    pub fn bits(&self) -> &BitSlice<u8> {
+        &self.bits
+    }
+
+    /// The bits packed LSB-first, zero-padded to a whole byte.
+    pub fn bytes(&self) -> &[u8] {
+        self.bits.as_raw_slice()
+    }
+
+    pub fn bit_count(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symbol_count
+    }
+
+    /// Packed size in bytes.
+    pub fn size(&self) -> usize {
+        self.bits.len().div_ceil(8)
+    }
+
+    /// Packed size over the input size (one byte per symbol), so below
+    /// 1.0 means the data shrank. 0.0 for empty input.
+    pub fn ratio(&self) -> f64 {
+        if self.symbol_count == 0 {
+            return 0.0;
+        }
+
+        self.size() as f64 / self.symbol_count as f64
+    }
+}
+
+impl Huffman {
+    /// Encodes one small packet headerlessly with a shared codec (see
+    /// `load_codec`), returning the bits, packed into bytes LSB-first,
+    /// with their bit and symbol counts. No table is included, so the
+    /// receiver decodes with the same codec.
+    pub fn encode_packet(&self, packet: &[u8]) -> Result<EncodedData, HuffmanError> {
+        let bits: BitVec<u8> = self.encode_bytes(packet)?.iter().by_vals().collect();
+
+        Ok(EncodedData { bits, symbol_count: packet.len() })
+    }
+
+    /// Decodes data from `encode_packet`. Fails like `decode_checked`, or
+    /// with `SymbolCountMismatch` if the bits don't decode to the
+    /// recorded number of symbols (e.g. a different codec was used).
+    pub fn decode_data(&self, encoded: &EncodedData) -> Result<Vec<u8>, HuffmanError> {
+        let decoded = self.decode_checked(encoded.bits())?;
+        if decoded.len() != encoded.symbol_count {
+            return Err(HuffmanError::SymbolCountMismatch {
+                expected: encoded.symbol_count,
+                actual: decoded.len(),
+            });
+        }
+
+        Ok(decoded)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_data_roundtrip() {
+        let input = b"self-describing encoded data".repeat(10);
+        let huffman = Huffman::from_bytes(input.clone());
+
+        let encoded = huffman.encode_packet(&input).unwrap();
+        assert_eq!(encoded.bit_count(), huffman.total_bits(&input).unwrap());
+        assert_eq!(encoded.symbol_count(), input.len());
+        assert_eq!(encoded.size(), encoded.bytes().len());
+        assert!(encoded.ratio() < 1.0);
+        assert_eq!(huffman.decode_data(&encoded).unwrap(), input);
+
+        let stored = encoded.bytes().to_vec();
+        let restored = EncodedData::from_parts(stored, encoded.bit_count(), input.len()).unwrap();
+        assert_eq!(restored, encoded);
+
+        let short = EncodedData::from_parts(vec![0], 9, 1);
+        assert_eq!(short, Err(HuffmanError::Truncated));
+    }
+
+    #[test]
+    fn symbol_count_must_match() {
+        let huffman = Huffman::from_bytes(b"aab".to_vec());
+        let encoded = huffman.encode_packet(b"aab").unwrap();
+        let wrong = EncodedData::from_parts(encoded.bytes().to_vec(), encoded.bit_count(), 2)
+            .unwrap();
+
+        let expected = HuffmanError::SymbolCountMismatch { expected: 2, actual: 3 };
+        assert_eq!(huffman.decode_data(&wrong), Err(expected));
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HuffmanError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidTable,
+    InvalidCode,
+    IncompleteCode { at_bit: usize },
+    InvalidLength,
+    NoTable,
+    InvalidMode(u8),
+    InvalidBlockMode(u8),
+    InvalidCodeLengths,
+    UnexpectedSymbol(u8),
+    BufferTooSmall,
+    InvalidFileName,
+    InvalidRange,
+    InvalidProbabilities,
+    ZeroFrequency(u8),
+    SymbolCountMismatch { expected: usize, actual: usize },
+    InvalidHex,
+    ReservedBytes,
+    RecordTooWide { index: usize, len: usize, width: usize },
+    InvalidTransform(u8),
+    MemoryLimitExceeded { estimated: usize, limit: usize },
+    ChecksumMismatch { expected: u32, actual: u32 },
+    RatioNotMet { archive_len: usize, input_len: usize },
+    DuplicateSymbol(u8),
+    IncompleteTree,
+    EmptyInput,
+    InvalidBase64,
+    TooManyStages(usize),
+    InvalidVarint,
+    BlockLengthMismatch { expected: usize, actual: usize },
+}
+
+impl HuffmanError {
+    /// For `RatioNotMet`, the ratio `compress_with_target` achieved: input
+    /// size over archive size. The variant holds the two lengths rather
+    /// than the float so the error stays `Eq`.
+    pub fn achieved(&self) -> Option<f64> {
+        match self {
+            HuffmanError::RatioNotMet { archive_len, input_len } => {
+                Some(*input_len as f64 / *archive_len as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HuffmanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HuffmanError::InvalidMagic => write!(f, "not a huffman archive"),
+            HuffmanError::UnsupportedVersion(version) => {
+                write!(f, "unsupported archive version {}", version)
+            }
+            HuffmanError::Truncated => write!(f, "archive ends unexpectedly"),
+            HuffmanError::InvalidTable => write!(f, "archive contains an invalid code table"),
+            HuffmanError::InvalidCode => write!(f, "payload contains a bit sequence with no code"),
+            HuffmanError::IncompleteCode { at_bit } => {
+                write!(f, "payload ends partway through a code at bit {}", at_bit)
+            }
+            HuffmanError::InvalidLength => {
+                write!(f, "decoded length is not a multiple of the value width")
+            }
+            HuffmanError::NoTable => write!(f, "no code table has been built for the input"),
+            HuffmanError::InvalidMode(mode) => write!(f, "unknown archive mode {}", mode),
+            HuffmanError::InvalidBlockMode(selector) => {
+                write!(f, "unknown block mode selector {}", selector)
+            }
+            HuffmanError::InvalidCodeLengths => {
+                write!(f, "code lengths do not describe a valid prefix code")
+            }
+            HuffmanError::UnexpectedSymbol(byte) => {
+                write!(f, "byte 0x{:02x} has no code in the table", byte)
+            }
+            HuffmanError::BufferTooSmall => write!(f, "output buffer is too small"),
+            HuffmanError::InvalidFileName => write!(f, "stored file name is not valid UTF-8"),
+            HuffmanError::InvalidRange => write!(f, "byte range is outside the archived data"),
+            HuffmanError::InvalidProbabilities => {
+                write!(f, "probabilities must be non-negative and sum to 1")
+            }
+            HuffmanError::ZeroFrequency(byte) => write!(f, "byte 0x{:02x} has a count of zero", byte),
+            HuffmanError::SymbolCountMismatch { expected, actual } => {
+                write!(f, "decoded {} symbols but the header records {}", actual, expected)
+            }
+            HuffmanError::InvalidHex => write!(f, "input is not valid hex"),
+            HuffmanError::ReservedBytes => {
+                write!(f, "archives can't represent a codec with reserved bytes")
+            }
+            HuffmanError::RecordTooWide { index, len, width } => {
+                write!(f, "value {} compresses to {} bytes, wider than {}", index, len, width)
+            }
+            HuffmanError::InvalidTransform(selector) => {
+                write!(f, "unknown pipeline transform selector {}", selector)
+            }
+            HuffmanError::MemoryLimitExceeded { estimated, limit } => {
+                write!(f, "codec needs an estimated {} bytes, over the {} limit", estimated, limit)
+            }
+            HuffmanError::ChecksumMismatch { expected, actual } => {
+                write!(f, "decoded data has CRC-32 {:08x} but the footer records {:08x}",
+                    actual, expected)
+            }
+            HuffmanError::RatioNotMet { .. } => {
+                write!(f, "compression ratio {:.3} is below the target", self.achieved().unwrap())
+            }
+            HuffmanError::DuplicateSymbol(byte) => {
+                write!(f, "more than one code decodes to byte 0x{:02x}", byte)
+            }
+            HuffmanError::IncompleteTree => {
+                write!(f, "code tree has internal nodes with a missing child")
+            }
+            HuffmanError::EmptyInput => write!(f, "input is empty"),
+            HuffmanError::InvalidBase64 => write!(f, "input is not valid base64"),
+            HuffmanError::TooManyStages(count) => {
+                write!(f, "pipeline has {} stages, at most 255 fit the header", count)
+            }
+            HuffmanError::InvalidVarint => write!(f, "varint doesn't fit in 64 bits"),
+            HuffmanError::BlockLengthMismatch { expected, actual } => {
+                write!(f, "stored block holds {} bytes, the block directory says {}",
+                    actual, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HuffmanError {}
+
+impl From<HuffmanError> for std::io::Error {
+    fn from(err: HuffmanError) -> Self {
+        let kind = match err {
+            HuffmanError::UnexpectedSymbol(_) => std::io::ErrorKind::InvalidInput,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+
+        std::io::Error::new(kind, err)
+    }
+}
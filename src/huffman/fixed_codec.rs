@@ -0,0 +1,173 @@
+use super::HuffmanError;
+
+
+/// Longest code a `FixedCodec` supports.
+pub const MAX_FIXED_CODE_LEN: u8 = 32;
+
+/// Codec over a small alphabet whose code lengths are known at compile
+/// time. `new` is a `const fn`, so the canonical codes are computed by the
+/// compiler and encoding and decoding never allocate:
+///
+/// ```
+/// use huffman::FixedCodec;
+///
+/// const DNA: FixedCodec<4> = FixedCodec::new([(b'A', 1), (b'C', 2), (b'G', 3), (b'T', 3)]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FixedCodec<const N: usize> {
+    /// `(symbol, length, code)` in canonical order, code bits MSB-first.
+    entries: [(u8, u8, u32); N],
+    /// Index into `entries` for each byte, or `N` if it has no code.
+    slots: [usize; 256],
+}
+
+impl<const N: usize> FixedCodec<N> {
+    /// Assigns canonical codes to `(symbol, code length)` pairs. Panics
+    /// (at compile time when used in a `const`) if a length is 0 or above
+    /// `MAX_FIXED_CODE_LEN`, a symbol repeats, or the lengths are
+    /// over-subscribed.
+    pub const fn new(lengths: [(u8, u8); N]) -> Self {
+        // Insertion sort by (length, symbol); iterators aren't const
+        let mut sorted = lengths;
+        let mut i = 1;
+        while i < N {
+            let mut j = i;
+            while j > 0 && (sorted[j].1 < sorted[j - 1].1
+                || (sorted[j].1 == sorted[j - 1].1 && sorted[j].0 < sorted[j - 1].0)) {
+                let swap = sorted[j];
+                sorted[j] = sorted[j - 1];
+                sorted[j - 1] = swap;
+                j -= 1;
+            }
+            i += 1;
+        }
+
+        let mut entries = [(0u8, 0u8, 0u32); N];
+        let mut slots = [N; 256];
+        let mut code = 0u64;
+        let mut prev_len = 0u8;
+        let mut i = 0;
+        while i < N {
+            let (symbol, len) = sorted[i];
+            assert!(len > 0 && len <= MAX_FIXED_CODE_LEN, "code length out of range");
+            assert!(slots[symbol as usize] == N, "symbol appears twice");
+
+            if i > 0 {
+                code += 1;
+            }
+            code <<= len - prev_len;
+            assert!(code < 1 << len, "code lengths are over-subscribed");
+
+            entries[i] = (symbol, len, code as u32);
+            slots[symbol as usize] = i;
+            prev_len = len;
+            i += 1;
+        }
+
+        FixedCodec { entries, slots }
+    }
+
+    /// The code for `byte` as `(length, bits)`, bits MSB-first.
+    pub const fn code(&self, byte: u8) -> Option<(u8, u32)> {
+        let slot = self.slots[byte as usize];
+        if slot == N {
+            None
+        } else {
+            Some((self.entries[slot].1, self.entries[slot].2))
+        }
+    }
+
+    /// Encodes `data` into `out`, bits packed LSB-first, and returns the
+    /// number of bits written.
+    pub fn encode_into(&self, data: &[u8], out: &mut [u8]) -> Result<usize, HuffmanError> {
+        let mut bit = 0;
+
+        for byte in data {
+            let (len, code) = self.code(*byte).ok_or(HuffmanError::UnexpectedSymbol(*byte))?;
+            if bit + len as usize > out.len() * 8 {
+                return Err(HuffmanError::BufferTooSmall);
+            }
+
+            for i in (0..len).rev() {
+                let mask = 1 << (bit % 8);
+                if code >> i & 1 == 1 {
+                    out[bit / 8] |= mask;
+                } else {
+                    out[bit / 8] &= !mask;
+                }
+                bit += 1;
+            }
+        }
+
+        Ok(bit)
+    }
+
+    /// Decodes `bit_count` bits from `bits` into `out`, returning the
+    /// number of bytes written.
+    pub fn decode_into(&self, bits: &[u8], bit_count: usize, out: &mut [u8])
+        -> Result<usize, HuffmanError> {
+        if bit_count > bits.len() * 8 {
+            return Err(HuffmanError::Truncated);
+        }
+
+        let mut written = 0;
+        let mut code = 0u32;
+        let mut len = 0u8;
+
+        for bit in 0..bit_count {
+            code = code << 1 | u32::from(bits[bit / 8] >> (bit % 8) & 1);
+            len += 1;
+
+            if let Some(entry) = self.entries.iter().find(|entry| entry.1 == len && entry.2 == code) {
+                *out.get_mut(written).ok_or(HuffmanError::BufferTooSmall)? = entry.0;
+                written += 1;
+                code = 0;
+                len = 0;
+            } else if len >= MAX_FIXED_CODE_LEN {
+                return Err(HuffmanError::InvalidCode);
+            }
+        }
+
+        if len > 0 {
+            return Err(HuffmanError::IncompleteCode { at_bit: bit_count });
+        }
+
+        Ok(written)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DNA: FixedCodec<4> = FixedCodec::new([(b'T', 3), (b'A', 1), (b'G', 3), (b'C', 2)]);
+
+    #[test]
+    fn static_codec_assigns_canonical_codes() {
+        assert_eq!(DNA.code(b'A'), Some((1, 0b0)));
+        assert_eq!(DNA.code(b'C'), Some((2, 0b10)));
+        assert_eq!(DNA.code(b'G'), Some((3, 0b110)));
+        assert_eq!(DNA.code(b'T'), Some((3, 0b111)));
+        assert_eq!(DNA.code(b'N'), None);
+    }
+
+    #[test]
+    fn static_codec_roundtrip() {
+        let input = b"GATTACA";
+        let mut packed = [0u8; 4];
+        let mut decoded = [0u8; 16];
+
+        let bit_count = DNA.encode_into(input, &mut packed).unwrap();
+        assert_eq!(bit_count, 3 + 1 + 3 + 3 + 1 + 2 + 1);
+
+        let len = DNA.decode_into(&packed, bit_count, &mut decoded).unwrap();
+        assert_eq!(&decoded[..len], input);
+    }
+
+    #[test]
+    fn static_codec_reports_small_buffers_and_unknown_bytes() {
+        assert_eq!(DNA.encode_into(b"TTT", &mut [0u8; 1]), Err(HuffmanError::BufferTooSmall));
+        assert_eq!(DNA.encode_into(b"ACN", &mut [0u8; 4]), Err(HuffmanError::UnexpectedSymbol(b'N')));
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use super::Huffman;
+
+
+/// Accumulates byte counts over any number of chunks during a training
+/// phase, then `freeze` builds the codec from the totals.
+#[derive(Debug, Default, Clone)]
+pub struct GrowingCounter {
+    counts: HashMap<u8, usize>,
+}
+
+impl GrowingCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            *self.counts.entry(*byte).or_insert(0) += 1;
+        }
+    }
+
+    /// Builds the codec from everything added so far. The codec holds no
+    /// input, so use `encode_bytes` rather than `encode` with it.
+    pub fn freeze(self) -> Huffman {
+        Huffman::from_counts(Vec::new(), self.counts)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frozen_codec_matches_concatenated_input() {
+        let chunks: [&[u8]; 3] = [b"first chunk, ", b"then a second, ", b"and a third one"];
+
+        let mut counter = GrowingCounter::new();
+        for chunk in chunks {
+            counter.add(chunk);
+        }
+        let frozen = counter.freeze();
+        let whole = Huffman::from_bytes(chunks.concat());
+
+        assert_eq!(frozen.table(), whole.table());
+    }
+}
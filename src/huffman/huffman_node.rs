@@ -1,7 +1,20 @@
+use bitvec::prelude::*;
+use serde::Serialize;
 use std::collections::{BinaryHeap, HashMap};
+use std::io::{self, Read};
 
+use super::HuffmanError;
 
-#[derive(PartialEq, Eq, Debug)]
+/// Total count probabilities are scaled to in `from_probabilities`.
+const PROBABILITY_SCALE: f64 = (1u64 << 32) as f64;
+/// How far from 1.0 a probability distribution may sum.
+const PROBABILITY_TOLERANCE: f64 = 1e-6;
+
+/// Bytes read per chunk by `count_bytes_with_progress`.
+const PROGRESS_CHUNK_LEN: usize = 64 * 1024;
+
+
+#[derive(PartialEq, Eq, Debug, Default)]
 pub struct HuffmanNode {
     pub byte: Option<u8>,
     pub count: usize,
@@ -10,27 +23,254 @@ pub struct HuffmanNode {
 }
 
 
+/// Reversed so `BinaryHeap` pops the lowest count first. Equal counts
+/// pop leaves before internal nodes, and leaves in byte order, so a given
+/// frequency map always builds the same tree.
 impl Ord for HuffmanNode{
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.count.cmp(&self.count)
+        let key = |node: &Self| (node.count, node.byte.is_none(), node.byte);
+        key(other).cmp(&key(self))
     }
 }
 
 impl PartialOrd for HuffmanNode{
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.count.cmp(&self.count))
+        Some(self.cmp(other))
     }
 }
 
 type MinHeap = BinaryHeap<HuffmanNode>;
 
+/// Heap entry for `new_with_seed`: ordered by count like `HuffmanNode`,
+/// with equal counts broken by a random key.
+struct SeededNode {
+    node: HuffmanNode,
+    key: u64,
+}
+
+impl Ord for SeededNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.node.count.cmp(&self.node.count).then(other.key.cmp(&self.key))
+    }
+}
+
+impl PartialOrd for SeededNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SeededNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for SeededNode {}
+
+/// splitmix64, enough to shuffle tie-breaks reproducibly.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// JSON-friendly copy of a tree for visualisation frontends. Leaves have a
+/// `symbol` and no `children`; internal nodes have two children, left
+/// (`0`) first.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct TreeView {
+    pub symbol: Option<u8>,
+    pub count: usize,
+    pub children: Vec<TreeView>,
+}
+
+/// One merge during tree construction, from `HuffmanNode::build_steps`:
+/// the counts of the two nodes popped off the heap (left first) and of
+/// the parent pushed back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BuildStep {
+    pub left: usize,
+    pub right: usize,
+    pub parent: usize,
+}
+
 impl HuffmanNode {
-    pub fn new(input: &String) -> Self{
-        let counts = Self::count_chars(input);
+    pub fn new(input: &[u8]) -> Self{
+        let counts = Self::count_bytes(input);
         let mut min_heap = Self::build_min_heap(counts);
         Self::build_tree(&mut min_heap)
     }
 
+    /// Builds a tree from a probability per symbol instead of counts, for
+    /// comparing against model-driven coders. Probabilities are scaled to
+    /// integer counts (keeping every non-zero one at least 1); symbols with
+    /// probability 0 get no code. Fails with `InvalidProbabilities` if any
+    /// is negative or not finite, or they don't sum to 1.0.
+    pub fn from_probabilities(probabilities: &[(u8, f64)]) -> Result<Self, HuffmanError> {
+        let total: f64 = probabilities.iter().map(|(_, p)| p).sum();
+        if probabilities.iter().any(|(_, p)| !p.is_finite() || *p < 0.0)
+            || (total - 1.0).abs() > PROBABILITY_TOLERANCE {
+            return Err(HuffmanError::InvalidProbabilities);
+        }
+
+        let mut counts = HashMap::new();
+        for (byte, p) in probabilities {
+            if *p > 0.0 {
+                counts.insert(*byte, ((p * PROBABILITY_SCALE).round() as usize).max(1));
+            }
+        }
+
+        let mut min_heap = Self::build_min_heap(counts);
+        Ok(Self::build_tree(&mut min_heap))
+    }
+
+    /// Like `new`, but frequency ties are broken by an RNG seeded with
+    /// `seed`, so each seed gives its own codebook and the same seed
+    /// always gives the same one. For experimenting with tie-breaking.
+    pub fn new_with_seed(input: &[u8], seed: u64) -> Self {
+        let mut counts: Vec<(u8, usize)> = Self::count_bytes(input).into_iter().collect();
+        counts.sort_unstable();
+
+        let mut state = seed;
+        let mut heap: BinaryHeap<SeededNode> = counts
+            .into_iter()
+            .map(|(byte, count)| SeededNode {
+                node: HuffmanNode { byte: Some(byte), count, left: None, right: None },
+                key: next_random(&mut state),
+            })
+            .collect();
+
+        while heap.len() > 1 {
+            let left = heap.pop().unwrap().node;
+            let right = heap.pop().unwrap().node;
+
+            heap.push(SeededNode {
+                node: HuffmanNode {
+                    byte: None,
+                    count: left.count + right.count,
+                    left: Some(Box::new(left)),
+                    right: Some(Box::new(right)),
+                },
+                key: next_random(&mut state),
+            });
+        }
+
+        heap.pop().map_or_else(HuffmanNode::default, |entry| entry.node)
+    }
+
+    pub fn to_view(&self) -> TreeView {
+        TreeView {
+            symbol: self.byte,
+            count: self.count,
+            children: [&self.left, &self.right]
+                .into_iter()
+                .flatten()
+                .map(|child| child.to_view())
+                .collect(),
+        }
+    }
+
+    /// Number of edges on the longest root-to-leaf path.
+    pub fn height(&self) -> usize {
+        let left = self.left.as_ref().map_or(0, |node| node.height() + 1);
+        let right = self.right.as_ref().map_or(0, |node| node.height() + 1);
+
+        left.max(right)
+    }
+
+    /// Whether every internal node has exactly two children, i.e. every
+    /// bit sequence leads somewhere. A tree rebuilt from an incomplete
+    /// code (e.g. from a damaged header) has dead ends that no bits can
+    /// decode through. A lone leaf and the empty tree count as complete,
+    /// as does a root whose only child is a leaf: the shape `from_table`
+    /// gives a single symbol with its 1-bit code.
+    pub fn is_complete(&self) -> bool {
+        if let (Some(only), None) | (None, Some(only)) = (&self.left, &self.right) {
+            return self.byte.is_none() && only.byte.is_some();
+        }
+
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            match (&node.left, &node.right) {
+                (Some(left), Some(right)) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                (None, None) => {}
+                _ => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Rebuilds a tree from byte → code pairs, e.g. codes read from a
+    /// header. Leaf counts are unknown and left at 0. Fails with
+    /// `InvalidTable` if one code is a prefix of another. Each code is
+    /// walked in a loop rather than recursively, so the deepest tree a
+    /// header can describe doesn't grow the call stack.
+    pub fn from_table(table: &HashMap<u8, BitVec>) -> Result<Self, HuffmanError> {
+        let mut root = HuffmanNode::default();
+
+        for (byte, code) in table {
+            let mut node = &mut root;
+            for bit in code.iter().by_vals() {
+                if node.byte.is_some() {
+                    return Err(HuffmanError::InvalidTable);
+                }
+                let child = if bit { &mut node.right } else { &mut node.left };
+                node = child.get_or_insert_with(Box::default);
+            }
+
+            if node.byte.is_some() || node.left.is_some() || node.right.is_some() {
+                return Err(HuffmanError::InvalidTable);
+            }
+            node.byte = Some(*byte);
+        }
+
+        Ok(root)
+    }
+
+    /// Replays `build_tree` on `counts` and records each merge in order,
+    /// for stepping through or animating the construction.
+    pub fn build_steps(counts: HashMap<u8, usize>) -> Vec<BuildStep> {
+        let mut min_heap = Self::build_min_heap(counts);
+        let mut steps = Vec::new();
+
+        while min_heap.len() > 1 {
+            let left = min_heap.pop().unwrap();
+            let right = min_heap.pop().unwrap();
+            let parent = left.count + right.count;
+
+            steps.push(BuildStep { left: left.count, right: right.count, parent });
+            min_heap.push(HuffmanNode {
+                byte: None,
+                count: parent,
+                left: Some(Box::new(left)),
+                right: Some(Box::new(right)),
+            });
+        }
+
+        steps
+    }
+
+    /// The initial heap built from `counts`, as `(byte, count)` in the
+    /// order `build_tree` pops it, for checking or explaining where the
+    /// construction starts. A debugging aid; nothing else needs it.
+    pub fn heap_snapshot(counts: HashMap<u8, usize>) -> Vec<(Option<u8>, usize)> {
+        let mut min_heap = Self::build_min_heap(counts);
+        let mut snapshot = Vec::with_capacity(min_heap.len());
+        while let Some(node) = min_heap.pop() {
+            snapshot.push((node.byte, node.count));
+        }
+
+        snapshot
+    }
+
     pub fn build_tree(min_heap: &mut MinHeap) -> Self{
         // Handle empty input
         if min_heap.is_empty() {
@@ -60,22 +300,107 @@ impl HuffmanNode {
     }
 
     pub fn count_chars(input: &str) -> HashMap<u8, usize> {
+        Self::count_bytes(input.as_bytes())
+    }
+
+    /// Counts bytes with ASCII uppercase folded to lowercase, for text
+    /// analysis. This is lossy: a tree built from these counts has no
+    /// codes for uppercase letters, so never use it for compression.
+    pub fn count_chars_case_insensitive(input: &str) -> HashMap<u8, usize> {
+        Self::count_bytes(input.to_ascii_lowercase().as_bytes())
+    }
+
+    pub fn count_bytes(input: &[u8]) -> HashMap<u8, usize> {
         let mut counts = HashMap::new();
-        input.as_bytes().iter().for_each(|char| {
-            if counts.contains_key(char) {
-                let char_freq = counts.get_mut(char).unwrap();
-                *char_freq += 1;
+        input.iter().for_each(|byte| {
+            if counts.contains_key(byte) {
+                let byte_freq = counts.get_mut(byte).unwrap();
+                *byte_freq += 1;
             }else {
-                counts.insert(char.clone(), 1);
+                counts.insert(*byte, 1);
             }
         });
 
         counts
     }
 
+    /// Shannon entropy of `counts`, `-Σ p(x) log2 p(x)` bits per symbol:
+    /// the lower bound for `Huffman::average_code_length`. 0.0 for no
+    /// counts or a single symbol.
+    pub fn entropy(counts: &HashMap<u8, usize>) -> f64 {
+        let total: usize = counts.values().sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        counts
+            .values()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+            // A single symbol gives -1 × log2(1) = -0.0
+            .max(0.0)
+    }
+
+    /// Counts the bytes from `reader`, calling `progress` after every
+    /// chunk with the total bytes processed and distinct bytes seen so
+    /// far, e.g. to drive a progress bar during the frequency pass.
+    pub fn count_bytes_with_progress<R: Read, F: FnMut(usize, usize)>(mut reader: R,
+        mut progress: F) -> io::Result<HashMap<u8, usize>> {
+        let mut counts = HashMap::new();
+        let mut buffer = vec![0u8; PROGRESS_CHUNK_LEN];
+        let mut processed = 0;
+
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            for byte in &buffer[..read] {
+                *counts.entry(*byte).or_insert(0) += 1;
+            }
+            processed += read;
+            progress(processed, counts.len());
+        }
+
+        Ok(counts)
+    }
+
+    /// Scales counts proportionally so the largest becomes `max_count`,
+    /// keeping every present symbol at a count of at least 1. Small counts
+    /// (e.g. fitting in a `u8` with `max_count = 255`) make for compact
+    /// headers while barely changing the resulting code lengths.
+    pub fn quantize_counts(counts: &HashMap<u8, usize>, max_count: usize)
+        -> HashMap<u8, usize> {
+        let largest = counts.values().copied().max().unwrap_or(0);
+        if largest <= max_count {
+            return counts.clone();
+        }
+
+        counts
+            .iter()
+            .map(|(byte, count)| {
+                let scaled = (*count as u128 * max_count as u128 + largest as u128 / 2)
+                    / largest as u128;
+                (*byte, (scaled as usize).max(1))
+            })
+            .collect()
+    }
+
     pub fn build_min_heap(counts: HashMap<u8, usize>) -> MinHeap {
         let mut min_heap = BinaryHeap::new();
 
+        // Push in symbol order, not hash order, so equal counts resolve
+        // the same way on every run
+        let mut counts: Vec<(u8, usize)> = counts.into_iter().collect();
+        counts.sort_unstable();
+
         for item in counts {
             let node = HuffmanNode {
                 byte: Some(item.0),
@@ -114,6 +439,41 @@ mod tests {
         assert_eq!(counts.len(), 0);
     }
 
+    #[test]
+    fn test_count_chars_case_insensitive() {
+        let counts = HuffmanNode::count_chars_case_insensitive("AaAa");
+
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts.get(&b'a'), Some(&4));
+    }
+
+    #[test]
+    fn test_from_table_rejects_prefix_conflict() {
+        let mut table = HashMap::new();
+        table.insert(b'a', bitvec![0]);
+        table.insert(b'b', bitvec![0, 1]);
+
+        assert_eq!(HuffmanNode::from_table(&table), Err(HuffmanError::InvalidTable));
+    }
+
+    #[test]
+    fn test_to_view_serializes_to_nested_json() {
+        let root = HuffmanNode::new(b"aab");
+        let json = serde_json::to_value(root.to_view()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "symbol": null,
+                "count": 3,
+                "children": [
+                    { "symbol": b'b', "count": 1, "children": [] },
+                    { "symbol": b'a', "count": 2, "children": [] },
+                ],
+            })
+        );
+    }
+
     #[test]
     fn test_build_tree_root_properties() {
         let mut counts = HashMap::new();
@@ -144,4 +504,164 @@ mod tests {
         assert_eq!(root.count, 5);
         assert_eq!(root.byte, Some(b'a'));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_quantize_counts() {
+        let mut counts = HashMap::new();
+        counts.insert(b'a', 10_000);
+        counts.insert(b'b', 5_000);
+        counts.insert(b'c', 1);
+
+        let quantized = HuffmanNode::quantize_counts(&counts, 255);
+
+        assert_eq!(quantized.get(&b'a'), Some(&255));
+        assert_eq!(quantized.get(&b'b'), Some(&128));
+        // Rare symbols never drop out of the alphabet
+        assert_eq!(quantized.get(&b'c'), Some(&1));
+    }
+
+    #[test]
+    fn test_count_bytes_with_progress() {
+        let input: Vec<u8> = (0..=255u8).cycle().take(PROGRESS_CHUNK_LEN * 2 + 100).collect();
+        let mut calls = Vec::new();
+
+        let counts = HuffmanNode::count_bytes_with_progress(&input[..], |processed, distinct| {
+            calls.push((processed, distinct));
+        })
+        .unwrap();
+
+        assert_eq!(counts, HuffmanNode::count_bytes(&input));
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(calls.last(), Some(&(input.len(), 256)));
+    }
+
+    #[test]
+    fn test_new_with_seed() {
+        let input = b"abcdefgh";
+
+        assert_eq!(HuffmanNode::new_with_seed(input, 7), HuffmanNode::new_with_seed(input, 7));
+
+        let first = HuffmanNode::new_with_seed(input, 0);
+        assert!((1..16).any(|seed| HuffmanNode::new_with_seed(input, seed) != first));
+        assert_eq!(first.height(), 3);
+    }
+
+    #[test]
+    fn test_from_probabilities() {
+        let distribution = [(b'a', 0.4), (b'b', 0.3), (b'c', 0.15), (b'd', 0.1), (b'e', 0.05)];
+        let tree = HuffmanNode::from_probabilities(&distribution).unwrap();
+
+        let mut table = HashMap::new();
+        crate::huffman::Huffman::make_table(&mut table, &tree, &mut bitvec![]);
+
+        for (byte, p) in distribution {
+            let ideal = -f64::log2(p);
+            assert!((table[&byte].len() as f64 - ideal.ceil()).abs() <= 1.0);
+        }
+
+        let dyadic = [(b'a', 0.5), (b'b', 0.25), (b'c', 0.125), (b'd', 0.125)];
+        let tree = HuffmanNode::from_probabilities(&dyadic).unwrap();
+        let mut table = HashMap::new();
+        crate::huffman::Huffman::make_table(&mut table, &tree, &mut bitvec![]);
+        for (byte, p) in dyadic {
+            assert_eq!(table[&byte].len() as f64, (-f64::log2(p)).ceil());
+        }
+    }
+
+    #[test]
+    fn test_from_probabilities_rejects_bad_distributions() {
+        let short = [(b'a', 0.5), (b'b', 0.4)];
+        let negative = [(b'a', 1.5), (b'b', -0.5)];
+        let nan = [(b'a', f64::NAN)];
+
+        for bad in [&short[..], &negative, &nan] {
+            let result = HuffmanNode::from_probabilities(bad);
+            assert_eq!(result, Err(HuffmanError::InvalidProbabilities));
+        }
+    }
+
+    #[test]
+    fn test_build_steps() {
+        let steps = HuffmanNode::build_steps(HuffmanNode::count_chars("aaaabbc"));
+
+        assert_eq!(steps, vec![
+            BuildStep { left: 1, right: 2, parent: 3 },
+            BuildStep { left: 3, right: 4, parent: 7 },
+        ]);
+        assert!(HuffmanNode::build_steps(HuffmanNode::count_chars("a")).is_empty());
+    }
+
+    #[test]
+    fn test_heap_snapshot() {
+        let snapshot = HuffmanNode::heap_snapshot(HuffmanNode::count_chars("ddccbbaeeee"));
+
+        assert_eq!(snapshot, vec![
+            (Some(b'a'), 1),
+            (Some(b'b'), 2),
+            (Some(b'c'), 2),
+            (Some(b'd'), 2),
+            (Some(b'e'), 4),
+        ]);
+        assert!(snapshot.is_sorted_by_key(|(byte, count)| (*count, *byte)));
+        assert!(HuffmanNode::heap_snapshot(HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_equal_counts_build_identical_tables() {
+        let table = |input: &[u8]| {
+            let mut table = HashMap::new();
+            crate::Huffman::make_table(&mut table, &HuffmanNode::new(input), &mut bitvec![]);
+            table
+        };
+
+        // Every count ties with another symbol's or with an internal node's
+        let input = b"abcdefgh abcdefgh aabbccdd";
+        assert_eq!(table(input), table(input));
+
+        // c and d merge first (c on the left), then the leaves a and b
+        // before the internal node they tie with
+        let expected = HashMap::from([
+            (b'a', bitvec![1, 0]),
+            (b'b', bitvec![1, 1]),
+            (b'c', bitvec![0, 0]),
+            (b'd', bitvec![0, 1]),
+        ]);
+        assert_eq!(table(b"aabbcd"), expected);
+
+        let leaf = HuffmanNode { byte: Some(b'z'), count: 2, left: None, right: None };
+        let internal = HuffmanNode { count: 2, ..Default::default() };
+        let lower = HuffmanNode { byte: Some(b'a'), count: 2, left: None, right: None };
+        assert!(leaf > internal);
+        assert!(lower > leaf);
+    }
+
+    #[test]
+    fn test_entropy() {
+        let uniform: HashMap<u8, usize> = (0..16).map(|byte| (byte, 5)).collect();
+        assert!((HuffmanNode::entropy(&uniform) - 4.0).abs() < 1e-12);
+
+        // p = 1/2, 1/4, 1/4
+        let skewed = HashMap::from([(b'a', 2), (b'b', 1), (b'c', 1)]);
+        assert!((HuffmanNode::entropy(&skewed) - 1.5).abs() < 1e-12);
+
+        assert_eq!(HuffmanNode::entropy(&HashMap::new()), 0.0);
+        assert_eq!(HuffmanNode::entropy(&HashMap::from([(b'a', 10)])), 0.0);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(HuffmanNode::new(b"aaaabbc").is_complete());
+        assert!(HuffmanNode::new(b"a").is_complete());
+        assert!(HuffmanNode::new(b"").is_complete());
+
+        // "1" has a child for "10" but none for "11"
+        let table = HashMap::from([(b'a', bitvec![0]), (b'b', bitvec![1, 0])]);
+        assert!(!HuffmanNode::from_table(&table).unwrap().is_complete());
+
+        // A single symbol's 1-bit code, but not a longer one
+        let table = HashMap::from([(b'a', bitvec![1])]);
+        assert!(HuffmanNode::from_table(&table).unwrap().is_complete());
+        let table = HashMap::from([(b'a', bitvec![0, 0])]);
+        assert!(!HuffmanNode::from_table(&table).unwrap().is_complete());
+    }
+}
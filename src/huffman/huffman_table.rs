@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use bitvec::prelude::*;
+
+use super::HuffmanError;
+
+
+/// Decode-side code table, code → byte, as read back from an archive
+/// header. The encode side needs the reverse, byte → code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HuffmanTable {
+    codes: HashMap<BitVec<u8>, u8>,
+}
+
+impl HuffmanTable {
+    pub fn new(codes: HashMap<BitVec<u8>, u8>) -> Self {
+        HuffmanTable { codes }
+    }
+
+    /// Parses the table from an archive header (see `reconstruct_table`).
+    pub fn from_header(header_bytes: &[u8]) -> Result<Self, HuffmanError> {
+        let (header, _) = crate::archive::read_header(header_bytes)?;
+
+        Ok(HuffmanTable { codes: header.table })
+    }
+
+    pub fn codes(&self) -> &HashMap<BitVec<u8>, u8> {
+        &self.codes
+    }
+
+    /// The byte → code map for encoding, with codes written as `0`s and
+    /// `1`s. Fails with `DuplicateSymbol` if two codes decode to the same
+    /// byte, which a well-formed table never has but a bad reconstruction
+    /// could.
+    pub fn invert(&self) -> Result<HashMap<u8, String>, HuffmanError> {
+        let mut inverted = HashMap::with_capacity(self.codes.len());
+
+        for (code, byte) in &self.codes {
+            let code = code.iter().by_vals().map(|bit| if bit { '1' } else { '0' }).collect();
+            if inverted.insert(*byte, code).is_some() {
+                return Err(HuffmanError::DuplicateSymbol(*byte));
+            }
+        }
+
+        Ok(inverted)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::compress;
+
+    #[test]
+    fn inverts_table_from_archive() {
+        let table = HuffmanTable::from_header(&compress(b"aaaabbc").0).unwrap();
+        let inverted = table.invert().unwrap();
+
+        assert_eq!(inverted.len(), 3);
+        assert_eq!(inverted[&b'a'].len(), 1);
+        for (code, byte) in table.codes() {
+            let bits = code.iter().by_vals().map(|bit| if bit { '1' } else { '0' });
+            assert!(inverted[byte].chars().eq(bits));
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_byte_targets() {
+        let table = HuffmanTable::new(HashMap::from([
+            (bitvec![u8, Lsb0; 0], b'x'),
+            (bitvec![u8, Lsb0; 1, 0], b'y'),
+            (bitvec![u8, Lsb0; 1, 1], b'x'),
+        ]));
+
+        assert_eq!(table.invert(), Err(HuffmanError::DuplicateSymbol(b'x')));
+    }
+}
@@ -0,0 +1,43 @@
+use bitvec::prelude::*;
+use std::collections::HashMap;
+
+
+/// Widest lookup window; longer codes fall back to walking the tree.
+pub const MAX_LUT_BITS: usize = 11;
+
+/// Decode lookup table indexed by the next `bits` bits of input (first
+/// bit in the lowest position). Each entry holds the symbol whose code
+/// those bits start with and the code's length, or `None` when the code
+/// is longer than the window.
+#[derive(Debug, Default)]
+pub struct DecodeLut {
+    pub bits: usize,
+    pub entries: Vec<Option<(u8, usize)>>,
+}
+
+impl DecodeLut {
+    pub fn new(table: &HashMap<u8, BitVec>) -> Self {
+        let max_len = table.values().map(|code| code.len()).max().unwrap_or(0);
+        let bits = max_len.min(MAX_LUT_BITS);
+        let mut entries = vec![None; 1 << bits];
+
+        for (byte, code) in table {
+            if code.len() > bits {
+                continue;
+            }
+
+            let prefix = code
+                .iter()
+                .by_vals()
+                .enumerate()
+                .fold(0, |index, (i, bit)| index | (usize::from(bit) << i));
+
+            // Every window starting with this code decodes to it
+            for suffix in 0..1 << (bits - code.len()) {
+                entries[prefix | (suffix << code.len())] = Some((*byte, code.len()));
+            }
+        }
+
+        DecodeLut { bits, entries }
+    }
+}
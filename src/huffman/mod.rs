@@ -1,95 +1,773 @@
 use bitvec::prelude::*;
 use std::collections::HashMap;
 
+use crate::io::{self, pack_bits};
+
+mod canonical;
+pub use canonical::canonical_codes;
+pub(crate) use canonical::{check_code_lengths, code_lengths};
+mod codec_builder;
+pub use codec_builder::CodecBuilder;
+mod code_builder;
+pub use code_builder::{BalancedHuffman, CodeBuilder, ShannonFano, StandardHuffman};
+mod diff;
+pub use diff::SymbolDiff;
+mod encoded_data;
+pub use encoded_data::EncodedData;
+mod error;
+pub use error::HuffmanError;
+mod fixed_codec;
+pub use fixed_codec::FixedCodec;
+mod growing_counter;
+pub use growing_counter::GrowingCounter;
 mod huffman_node;
-use huffman_node::HuffmanNode;
+pub use huffman_node::{BuildStep, HuffmanNode, TreeView};
+mod huffman_table;
+pub use huffman_table::HuffmanTable;
+mod lut;
+use lut::DecodeLut;
+
 
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Huffman {
+    root: HuffmanNode,
     table: HashMap<u8, BitVec>,
-    input: String,
+    input: Vec<u8>,
     encoded: BitVec,
+    lut: Option<DecodeLut>,
+    /// Bytes always written as the escape code plus an 8-bit literal (see
+    /// `CodecBuilder::reserve`). The first one's leaf is the escape code.
+    reserved: Vec<u8>,
 }
 
 impl Huffman{
-    pub fn new(input: &String) -> Self {
-        let root = HuffmanNode::new(&input);
-        let input = input.clone();
-        let mut huffman = Huffman {
-            table: HashMap::new(),
+    /// Builds a codec for `input`, failing with `EmptyInput` if there is
+    /// nothing to build a tree from. `from_bytes` accepts empty input and
+    /// gives a codec with an empty table.
+    pub fn try_new(input: &str) -> Result<Self, HuffmanError> {
+        if input.is_empty() {
+            return Err(HuffmanError::EmptyInput);
+        }
+
+        Ok(Self::from_bytes(input.as_bytes().to_vec()))
+    }
+
+    pub fn from_bytes(input: Vec<u8>) -> Self {
+        let counts = HuffmanNode::count_bytes(&input);
+        Self::from_counts(input, counts)
+    }
+
+    /// Like `from_bytes`, but builds the tree from frequencies scaled down
+    /// to at most `max_count` (see `HuffmanNode::quantize_counts`).
+    pub fn with_quantized_counts(input: Vec<u8>, max_count: usize) -> Self {
+        let counts = HuffmanNode::count_bytes(&input);
+        let quantized = HuffmanNode::quantize_counts(&counts, max_count);
+        Self::from_counts(input, quantized)
+    }
+
+    /// Builds the code tree with `builder` instead of standard Huffman.
+    pub fn with_builder<B: CodeBuilder>(input: Vec<u8>, builder: &B) -> Self {
+        let counts = HuffmanNode::count_bytes(&input);
+        Self::build(input, counts, builder)
+    }
+
+    /// Builds a codec from counts supplied by the caller rather than
+    /// counted from input. A symbol with a zero count would only waste a
+    /// code, so it is left out, or in `strict` mode rejected with
+    /// `ZeroFrequency`.
+    pub fn with_counts(counts: HashMap<u8, usize>, strict: bool) -> Result<Self, HuffmanError> {
+        if strict {
+            let zero = counts.iter().filter(|(_, count)| **count == 0).map(|(byte, _)| *byte).min();
+            if let Some(byte) = zero {
+                return Err(HuffmanError::ZeroFrequency(byte));
+            }
+        }
+
+        Ok(Self::from_counts(Vec::new(), counts))
+    }
+
+    /// Zero counts are dropped; see `with_counts`.
+    pub(crate) fn from_counts(input: Vec<u8>, mut counts: HashMap<u8, usize>) -> Self {
+        counts.retain(|_, count| *count > 0);
+        Self::build(input, counts, &StandardHuffman)
+    }
+
+    fn build<B: CodeBuilder>(input: Vec<u8>, counts: HashMap<u8, usize>, builder: &B) -> Self {
+        let root = builder.build(counts);
+        let mut table = HashMap::new();
+        Self::make_table(&mut table, &root, &mut bitvec![]);
+
+        Huffman {
+            root,
+            table,
             input,
             encoded: bitvec![],
+            lut: None,
+            reserved: Vec::new(),
+        }
+    }
+
+    /// Builds a codec from `(symbol, code length)` pairs produced elsewhere
+    /// (e.g. a DEFLATE block), assigning canonical codes. Lengths must be
+    /// non-zero, symbols unique, and the set must have a Kraft sum of
+    /// exactly 1, so that the codes are prefix-free and every bit sequence
+    /// decodes. A lone symbol takes a 1-bit code.
+    pub fn from_code_lengths(lengths: &[(u8, u8)]) -> Result<Self, HuffmanError> {
+        let lengths: Vec<(u8, usize)> = lengths
+            .iter()
+            .map(|(byte, len)| (*byte, *len as usize))
+            .collect();
+        check_code_lengths(&lengths)?;
+
+        Self::from_table(canonical_codes(&lengths))
+    }
+
+    /// Builds a codec around an existing byte → code table, rebuilding
+    /// the tree from it. Fails like `HuffmanNode::from_table`.
+    pub(crate) fn from_table(table: HashMap<u8, BitVec>) -> Result<Self, HuffmanError> {
+        let root = HuffmanNode::from_table(&table)?;
+
+        Ok(Huffman {
+            root,
+            table,
+            ..Default::default()
+        })
+    }
+
+    /// Rebuilds a codec from a table read back with `read_huffman_file`,
+    /// so a `.huff` file decodes without the codec that wrote it. Fails
+    /// with `DuplicateSymbol` if two codes map to one byte, or like
+    /// `HuffmanNode::from_table`.
+    pub fn from_serialized_table(table: &HuffmanTable) -> Result<Self, HuffmanError> {
+        let mut codes = HashMap::with_capacity(table.codes().len());
+        for (code, byte) in table.codes() {
+            if codes.insert(*byte, code.iter().by_vals().collect()).is_some() {
+                return Err(HuffmanError::DuplicateSymbol(*byte));
+            }
+        }
+
+        Self::from_table(codes)
+    }
+
+    /// The plaintext the codec was built from; `encode` leaves it intact.
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    /// Bits produced by the last `encode` call, empty before the first.
+    pub fn encoded(&self) -> &BitVec {
+        &self.encoded
+    }
+
+    pub fn table(&self) -> &HashMap<u8, BitVec> {
+        &self.table
+    }
+
+    /// The code for `byte`, first bit first, as a byte-backed `BitVec` for
+    /// assembling custom bitstreams. `None` if the byte has no code.
+    pub fn code_bits(&self, byte: u8) -> Option<BitVec<u8>> {
+        self.table.get(&byte).map(|code| code.iter().by_vals().collect())
+    }
+
+    /// `(symbol, code length)` for every symbol, in symbol order: all a
+    /// canonical code needs, one byte per symbol. `from_code_lengths`
+    /// rebuilds the codec `canonicalize` produces from it.
+    pub fn canonical_table(&self) -> Vec<(u8, u8)> {
+        let mut lengths: Vec<(u8, u8)> = self
+            .table
+            .iter()
+            .map(|(byte, code)| (*byte, code.len() as u8))
+            .collect();
+        lengths.sort_unstable();
+
+        lengths
+    }
+
+    /// Reassigns codes in canonical form (see `canonical_codes`), keeping
+    /// each symbol's code length, so the same lengths always give the
+    /// same codes. The tree is rebuilt to match, with its counts kept.
+    pub fn canonicalize(&mut self) {
+        let lengths: Vec<(u8, usize)> = self
+            .table
+            .iter()
+            .map(|(byte, code)| (*byte, code.len()))
+            .collect();
+        let table = canonical_codes(&lengths);
+
+        let counts: HashMap<u8, usize> = self.leaf_counts().into_iter().collect();
+        let mut root = HuffmanNode::from_table(&table)
+            .expect("canonical codes with the tree's lengths are prefix-free");
+        Self::fill_counts(&mut root, &counts);
+
+        self.root = root;
+        self.table = table;
+        self.lut = None;
+    }
+
+    /// Sets each leaf's count from `counts` and each internal node's to
+    /// the sum of its children, returning the node's count.
+    fn fill_counts(node: &mut HuffmanNode, counts: &HashMap<u8, usize>) -> usize {
+        node.count = match node.byte {
+            Some(byte) => counts.get(&byte).copied().unwrap_or(0),
+            None => [&mut node.left, &mut node.right]
+                .into_iter()
+                .flatten()
+                .map(|child| Self::fill_counts(child, counts))
+                .sum(),
         };
 
-        huffman.make_table(Box::new(root),
-        &mut bitvec![]);
+        node.count
+    }
 
-        huffman
+    pub fn root(&self) -> &HuffmanNode {
+        &self.root
     }
 
-    pub fn make_table(&mut self, curr_node: Box<HuffmanNode>, 
+    /// Whether the codec escapes reserved bytes (see `CodecBuilder::reserve`).
+    pub fn has_reserved(&self) -> bool {
+        !self.reserved.is_empty()
+    }
+
+    /// Expected code length in bits per symbol, `Σ p[b] × len[b]`, with
+    /// `p` taken from the leaf counts in the tree. 0.0 if there are no
+    /// counts (e.g. a codec built from code lengths).
+    pub fn average_code_length(&self) -> f64 {
+        let counts = self.leaf_counts();
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        counts
+            .iter()
+            .map(|(byte, count)| *count as f64 * self.table[byte].len() as f64)
+            .sum::<f64>()
+            / total as f64
+    }
+
+    /// Checks Huffman's optimality bound against the leaf counts:
+    /// `entropy <= average_code_length < entropy + 1`. A single symbol
+    /// still needs a 1-bit code, so it sits exactly on the upper bound.
+    pub fn is_within_one_bit_of_entropy(&self) -> bool {
+        let counts: HashMap<u8, usize> = self.leaf_counts().into_iter().collect();
+        if counts.values().sum::<usize>() == 0 {
+            return true;
+        }
+
+        let entropy = HuffmanNode::entropy(&counts);
+        let average = self.average_code_length();
+
+        // Allow for rounding when the code is exactly optimal
+        average >= entropy - 1e-9 && (average < entropy + 1.0 || counts.len() == 1)
+    }
+
+    /// `(symbol, count)` for every leaf of the tree.
+    fn leaf_counts(&self) -> Vec<(u8, usize)> {
+        let mut counts = Vec::new();
+        let mut stack = vec![&self.root];
+
+        while let Some(node) = stack.pop() {
+            if let Some(byte) = node.byte {
+                counts.push((byte, node.count));
+            }
+            stack.extend(node.left.as_deref());
+            stack.extend(node.right.as_deref());
+        }
+
+        counts
+    }
+
+    /// Stable identifier for the codebook, e.g. as a cache key: an FNV-1a
+    /// hash of the (symbol, code length, code bits) entries in symbol
+    /// order, then of the reserved bytes if there are any, since they
+    /// change the encoding without changing the table. Equal codebooks
+    /// always give the same value, across runs and platforms; different
+    /// ones almost always differ.
+    pub fn fingerprint(&self) -> u64 {
+        let mut entries: Vec<_> = self.table.iter().collect();
+        entries.sort_by_key(|entry| *entry.0);
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut feed = |byte: u8| {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        };
+
+        for (byte, code) in entries {
+            feed(*byte);
+            feed(code.len() as u8);
+            for bit in code.iter().by_vals() {
+                feed(u8::from(bit));
+            }
+        }
+
+        // Only when present, so codecs without any keep their values
+        if !self.reserved.is_empty() {
+            for byte in (self.reserved.len() as u16).to_le_bytes() {
+                feed(byte);
+            }
+            for byte in &self.reserved {
+                feed(*byte);
+            }
+        }
+
+        hash
+    }
+
+    /// The leaf counts and codes as CSV, one `byte,char,count,code,
+    /// code_length` row per symbol under a header row, sorted by count
+    /// (highest first) then byte. `char` is the byte ASCII-escaped like
+    /// `\n` or `\xff`; `code` is the bits as `0`s and `1`s. Symbols the
+    /// tree has no count for (e.g. a codec built from code lengths) show 0.
+    pub fn counts_to_csv(&self) -> String {
+        let counts: HashMap<u8, usize> = self.leaf_counts().into_iter().collect();
+        let mut rows: Vec<(u8, usize, &BitVec)> = self
+            .table
+            .iter()
+            .map(|(byte, code)| (*byte, counts.get(byte).copied().unwrap_or(0), code))
+            .collect();
+        rows.sort_by_key(|(byte, count, _)| (std::cmp::Reverse(*count), *byte));
+
+        let mut csv = String::from("byte,char,count,code,code_length\n");
+        for (byte, count, code) in rows {
+            let bits: String = code
+                .iter()
+                .by_vals()
+                .map(|bit| if bit { '1' } else { '0' })
+                .collect();
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                byte,
+                csv_char(byte),
+                count,
+                bits,
+                code.len()
+            ));
+        }
+
+        csv
+    }
+
+    /// Emits the codebook as Rust source: a `const` array of
+    /// `(byte, code length, code bits packed LSB-first)` sorted by byte,
+    /// so a trained table can be baked into another crate. `name` must be
+    /// a valid Rust identifier.
+    pub fn export_rust_table(&self, name: &str) -> String {
+        let mut entries: Vec<_> = self.table.iter().collect();
+        entries.sort_by_key(|entry| *entry.0);
+
+        let mut source = format!(
+            "pub const {}: [(u8, u8, &[u8]); {}] = [\n",
+            name,
+            entries.len()
+        );
+        for (byte, code) in entries {
+            let packed: Vec<String> = pack_bits(code)
+                .iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect();
+            source.push_str(&format!(
+                "    (0x{:02x}, {}, &[{}]),\n",
+                byte,
+                code.len(),
+                packed.join(", ")
+            ));
+        }
+        source.push_str("];\n");
+
+        source
+    }
+
+    /// Walks the tree from `curr_node`, recording the code of every leaf
+    /// below it with `code` as the prefix. Uses an explicit stack rather
+    /// than recursion, so tree depth can't overflow the call stack.
+    ///
+    /// Debug builds also assert that each new code is prefix-free against
+    /// every code already in `table`; release builds skip the check.
+    pub fn make_table(table: &mut HashMap<u8, BitVec>, curr_node: &HuffmanNode,
         code: &mut BitVec) {
-        if let Some(byte) = curr_node.byte {
-            // Single character case: assign a code of [false] if code is empty
-            if code.is_empty() {
-                self.table.insert(byte, bitvec![0;1]);
-            } else {
-                self.table.insert(byte, code.clone());
+        let mut stack = vec![(curr_node, code.clone())];
+
+        while let Some((node, code)) = stack.pop() {
+            if let Some(byte) = node.byte {
+                // Single character case: assign a code of [false] if code is empty
+                let code = if code.is_empty() { bitvec![0;1] } else { code };
+
+                debug_assert!(
+                    table
+                        .values()
+                        .all(|other| !other.starts_with(&code) && !code.starts_with(other)),
+                    "code {} for byte 0x{:02x} conflicts with an existing code", code, byte,
+                );
+                table.insert(byte, code);
+
+                continue
+            }
+
+            if let Some(right) = &node.right {
+                let mut right_code = code.clone();
+                right_code.push(true);
+                stack.push((right, right_code));
             }
 
-            return
+            if let Some(left) = &node.left {
+                let mut left_code = code;
+                left_code.push(false);
+                stack.push((left, left_code));
+            }
         }
-    
-        if let Some(left) = curr_node.left {
-            code.push(false);
-            self.make_table(left, code);
-            code.pop();
+    }
+
+    /// Encoded size over original size, both in bits, for the last
+    /// `encode` call: below 1.0 means the encoding is smaller. `None`
+    /// before `encode` has run or for empty input.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.encoded.is_empty() || self.input.is_empty() {
+            return None;
         }
-    
-        if let Some(right) = curr_node.right {
-            code.push(true);
-            self.make_table(right, code);
-            code.pop();
+
+        Some(self.encoded.len() as f64 / (self.input.len() * 8) as f64)
+    }
+
+    pub fn encode(&mut self) -> Result<BitVec, HuffmanError> {
+        if self.table.is_empty() && !self.input.is_empty() {
+            return Err(HuffmanError::NoTable);
         }
+
+        let bits = self.encode_bytes(&self.input)?;
+        self.encoded = bits.clone();
+
+        Ok(bits)
+    }
+
+    /// Encodes the input into byte-backed bits, the form
+    /// `write_bits_to_file` takes. Unlike `encode` it leaves the cached
+    /// encoding alone.
+    pub fn encode_bits(&self) -> Result<BitVec<u8>, HuffmanError> {
+        if self.table.is_empty() && !self.input.is_empty() {
+            return Err(HuffmanError::NoTable);
+        }
+
+        Ok(self.encode_bytes(&self.input)?.iter().by_vals().collect())
+    }
+
+    /// Encodes arbitrary `data` with this codec's table, failing on the
+    /// first byte the table has no code for.
+    pub fn encode_bytes(&self, data: &[u8]) -> Result<BitVec, HuffmanError> {
+        // Sized up front so the loop never reallocates
+        let mut bits = BitVec::with_capacity(self.total_bits(data)?);
+        self.encode_into(data, &mut bits)?;
+
+        Ok(bits)
     }
-    
-    pub fn encode(&mut self) -> BitVec {
-        let mut bits = bitvec![];
 
-        self.input.bytes().for_each(|i_byte| {
+    /// Number of bits `data` encodes to, failing on the first byte the
+    /// table has no code for.
+    pub fn total_bits(&self, data: &[u8]) -> Result<usize, HuffmanError> {
+        data.iter().try_fold(0, |total, byte| {
+            if self.reserved.contains(byte) {
+                return Ok(total + self.table[&self.reserved[0]].len() + 8);
+            }
+
+            let code = self.table.get(byte).ok_or(HuffmanError::UnexpectedSymbol(*byte))?;
+            Ok(total + code.len())
+        })
+    }
+
+    /// Appends the codes for `data` to `bits`. Codes are copied from the
+    /// table by reference, so with enough capacity reserved in `bits` the
+    /// loop doesn't allocate at all.
+    pub fn encode_into(&self, data: &[u8], bits: &mut BitVec) -> Result<(), HuffmanError> {
+        for byte in data {
+            if self.reserved.contains(byte) {
+                bits.extend_from_bitslice(&self.table[&self.reserved[0]]);
+                bits.extend((0..8).rev().map(|i| byte >> i & 1 == 1));
+                continue;
+            }
+
             let code = self.table
-                .get(&i_byte)
-                .unwrap();
-            
-            bits.extend(code.iter());
-        });
+                .get(byte)
+                .ok_or(HuffmanError::UnexpectedSymbol(*byte))?;
 
-        self.encoded = bits.clone();
-    
-        bits
+            bits.extend_from_bitslice(code);
+        }
+
+        Ok(())
     }
-    
-    pub fn decode(&mut self, encoded: &BitVec) -> String {
-        let mut current = bitvec![];
+
+    /// Decodes by walking the stored tree: each bit selects a child and
+    /// reaching a leaf emits its byte and restarts from the root.
+    /// Trailing bits that don't complete a code are ignored; use
+    /// `decode_checked` to have them reported. Any other error, such as
+    /// `InvalidCode` or `IncompleteTree`, is returned.
+    pub fn decode(&mut self, encoded: &BitVec) -> Result<String, HuffmanError> {
         let mut output = String::new();
-    
-        for i in 0..encoded.len() {
-            let bit = encoded[i];
-            current.push(bit);
-        
-            self.table
+        Self::allow_trailing_bits(
+            self.walk(encoded.iter().by_vals(), |byte| output.push(byte as char)),
+        )?;
+
+        Ok(output)
+    }
+
+    /// Decodes bits as read by `read_huffman_file` (LSB-first within each
+    /// byte, as `write_bits_to_file` packs them). Like `decode`, trailing
+    /// bits that don't complete a code are ignored and other errors are
+    /// returned; use `decode_checked` to have the trailing bits reported.
+    pub fn decode_bits(&self, bits: &BitVec<u8>) -> Result<Vec<u8>, HuffmanError> {
+        let mut output = Vec::new();
+        Self::allow_trailing_bits(self.walk(bits.iter().by_vals(), |byte| output.push(byte)))?;
+
+        Ok(output)
+    }
+
+    /// Treats running out of bits partway through a code as the end of
+    /// the output, for the lenient decoders.
+    fn allow_trailing_bits(result: Result<(), HuffmanError>) -> Result<(), HuffmanError> {
+        match result {
+            Err(HuffmanError::IncompleteCode { .. }) => Ok(()),
+            other => other,
+        }
+    }
+
+    /// Decodes the bits stored by the last `encode` call, whose length is
+    /// kept with them, so no bits or bit count need to be passed back in.
+    /// Compare the result with `input`.
+    pub fn decode_stored(&self) -> Result<Vec<u8>, HuffmanError> {
+        self.decode_checked(&self.encoded)
+    }
+
+    /// Like `decode`, but returns bytes and fails on malformed input:
+    /// `IncompleteCode` with the bit offset where the input ran out
+    /// partway through a code, or `InvalidCode` for a path the tree
+    /// doesn't have.
+    pub fn decode_checked<T: BitStore, O: BitOrder>(&self, encoded: &BitSlice<T, O>)
+        -> Result<Vec<u8>, HuffmanError> {
+        let mut output = Vec::new();
+        self.walk(encoded.iter().by_vals(), |byte| output.push(byte))?;
+
+        Ok(output)
+    }
+
+    /// Decodes like `decode_checked`, but symbols with an entry in `dict`
+    /// are expanded to its phrase, for phrase-dictionary coding where one
+    /// symbol stands for a multi-byte sequence. Symbols without an entry
+    /// decode to themselves.
+    pub fn decode_with_dictionary<T: BitStore, O: BitOrder>(&self, encoded: &BitSlice<T, O>,
+        dict: &HashMap<u8, Vec<u8>>) -> Result<Vec<u8>, HuffmanError> {
+        let mut output = Vec::new();
+        self.walk(encoded.iter().by_vals(), |symbol| match dict.get(&symbol) {
+            Some(phrase) => output.extend_from_slice(phrase),
+            None => output.push(symbol),
+        })?;
+
+        Ok(output)
+    }
+
+    /// Calls `on_symbol` with each decoded byte as it is reached instead
+    /// of collecting them, for fold- or scan-style processing without an
+    /// output buffer. Fails like `decode_checked`; symbols before the
+    /// error have already been passed to `on_symbol`.
+    pub fn decode_each<T: BitStore, O: BitOrder>(&self, encoded: &BitSlice<T, O>,
+        on_symbol: impl FnMut(u8)) -> Result<(), HuffmanError> {
+        self.walk(encoded.iter().by_vals(), on_symbol)
+    }
+
+    /// Decodes `bit_count` bits packed into `bytes` in the given order,
+    /// for data read from elsewhere without going through `BitVec`. Bits
+    /// are read straight from `bytes` with a `BitIter`.
+    pub fn decode_packed(&self, bytes: &[u8], bit_count: usize, order: io::BitOrder)
+        -> Result<Vec<u8>, HuffmanError> {
+        if bit_count > bytes.len() * 8 {
+            return Err(HuffmanError::Truncated);
+        }
+
+        let mut output = Vec::new();
+        self.walk(io::BitIter::new(bytes, bit_count, order), |byte| output.push(byte))?;
+
+        Ok(output)
+    }
+
+    /// Decodes every bit of `bytes` (packed LSB-first) when no bit count
+    /// was stored. Fewer than 8 trailing zero bits that don't complete a
+    /// code are taken as padding; anything else left over fails with
+    /// `IncompleteCode`.
+    ///
+    /// Only unambiguous if zero padding can never complete a code, i.e.
+    /// the all-zeros code is at least 8 bits long, or streams always end
+    /// on a byte boundary. Canonical codes start with an all-zeros code,
+    /// so they usually need aligned streams.
+    pub fn decode_until_exhausted(&self, bytes: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        self.check_complete()?;
+        let bits = bytes.view_bits::<Lsb0>();
+        let mut output = Vec::new();
+        let mut pos = 0;
+
+        while pos < bits.len() {
+            match self.walk_one(&bits[pos..]) {
+                Ok((byte, len)) => {
+                    output.push(byte);
+                    pos += len;
+                }
+                Err(HuffmanError::IncompleteCode { .. })
+                    if bits.len() - pos < 8 && bits[pos..].not_any() => break,
+                Err(HuffmanError::IncompleteCode { at_bit }) => {
+                    return Err(HuffmanError::IncompleteCode { at_bit: pos + at_bit });
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Builds the lookup table used by `decode_lut` ahead of time, so the
+    /// first decode doesn't pay for it.
+    pub fn warm(&mut self) {
+        if self.lut.is_none() {
+            // The escape code is followed by a literal, so leave it to the tree walk
+            let mut table = self.table.clone();
+            if let Some(escape) = self.reserved.first() {
+                table.remove(escape);
+            }
+            self.lut = Some(DecodeLut::new(&table));
+        }
+    }
+
+    /// Table-driven decode: looks up several bits at once instead of
+    /// stepping through the tree per bit. Produces the same output and
+    /// errors as `decode_checked`. Builds the lookup table on first use
+    /// unless `warm` has already been called.
+    pub fn decode_lut<T: BitStore, O: BitOrder>(&mut self, encoded: &BitSlice<T, O>)
+        -> Result<Vec<u8>, HuffmanError> {
+        self.warm();
+        let lut = self.lut.as_ref().unwrap();
+        let mut output = Vec::new();
+        let mut pos = 0;
+
+        while pos < encoded.len() {
+            let window = lut.bits.min(encoded.len() - pos);
+            let index = encoded[pos..pos + window]
                 .iter()
-                .find(|entry| entry.1.eq(&current))
-                .and_then(|entry| {
-                    current.clear();
-                    let char = *entry.0 as char;
-                    output.push(char);
-                    Some(())
-                });
+                .by_vals()
+                .enumerate()
+                .fold(0, |index, (i, bit)| index | (usize::from(bit) << i));
+
+            match lut.entries[index] {
+                Some((byte, len)) if len <= window => {
+                    output.push(byte);
+                    pos += len;
+                }
+                // Code longer than the window, or cut off by the end
+                _ => {
+                    let (byte, len) = self.walk_one(&encoded[pos..]).map_err(|err| match err {
+                        HuffmanError::IncompleteCode { at_bit } => {
+                            HuffmanError::IncompleteCode { at_bit: pos + at_bit }
+                        }
+                        err => err,
+                    })?;
+                    output.push(byte);
+                    pos += len;
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Decodes the single symbol at the front of `encoded`, returning it
+    /// with its code length.
+    fn walk_one<T: BitStore, O: BitOrder>(&self, encoded: &BitSlice<T, O>)
+        -> Result<(u8, usize), HuffmanError> {
+        let mut node = &self.root;
+
+        for (i, bit) in encoded.iter().by_vals().enumerate() {
+            if node.byte.is_none() {
+                let child = if bit { &node.right } else { &node.left };
+                node = child.as_deref().ok_or(HuffmanError::InvalidCode)?;
+            }
+
+            if let Some(byte) = node.byte {
+                if self.reserved.first() == Some(&byte) {
+                    let literal = encoded
+                        .get(i + 1..i + 9)
+                        .ok_or(HuffmanError::IncompleteCode { at_bit: encoded.len() })?
+                        .iter()
+                        .by_vals()
+                        .fold(0, |acc, bit| acc << 1 | u8::from(bit));
+                    return Ok((literal, i + 9));
+                }
+
+                return Ok((byte, i + 1));
+            }
+        }
+
+        Err(HuffmanError::IncompleteCode { at_bit: encoded.len() })
+    }
+
+    /// Tree decoders refuse incomplete trees up front (see
+    /// `HuffmanNode::is_complete`) rather than failing partway through.
+    fn check_complete(&self) -> Result<(), HuffmanError> {
+        if !self.root.is_complete() {
+            return Err(HuffmanError::IncompleteTree);
         }
-    
-        output
+
+        Ok(())
+    }
+
+    fn walk(&self, mut encoded: impl Iterator<Item = bool>, mut emit: impl FnMut(u8))
+        -> Result<(), HuffmanError> {
+        self.check_complete()?;
+        let mut node = &self.root;
+        let mut bit_count = 0;
+
+        while let Some(bit) = encoded.next() {
+            bit_count += 1;
+
+            // A single-leaf tree has no children: every bit is the lone symbol
+            if node.byte.is_none() {
+                let child = if bit { &node.right } else { &node.left };
+                match child {
+                    Some(child) => node = child,
+                    None => return Err(HuffmanError::InvalidCode),
+                }
+            }
+
+            if let Some(byte) = node.byte {
+                if self.reserved.first() == Some(&byte) {
+                    let mut literal = 0u8;
+                    for _ in 0..8 {
+                        let bit = encoded.next()
+                            .ok_or(HuffmanError::IncompleteCode { at_bit: bit_count })?;
+                        literal = literal << 1 | u8::from(bit);
+                        bit_count += 1;
+                    }
+                    emit(literal);
+                } else {
+                    emit(byte);
+                }
+                node = &self.root;
+            }
+        }
+
+        if !std::ptr::eq(node, &self.root) {
+            return Err(HuffmanError::IncompleteCode { at_bit: bit_count });
+        }
+
+        Ok(())
+    }
+}
+
+/// `byte` as printable text for a CSV field, quoted if it contains a
+/// comma or a quote.
+fn csv_char(byte: u8) -> String {
+    let text = byte.escape_ascii().to_string();
+    if text.contains([',', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
     }
 }
 
@@ -101,45 +779,179 @@ mod tests {
     #[test]
     fn encode_decode_roundtrip() {
         let input = "hello world".to_string();
-        let mut huffman = Huffman::new(&input.clone());
+        let mut huffman = Huffman::try_new(&input).unwrap();
 
-        let encoded = huffman.encode();
+        let encoded = huffman.encode().unwrap();
         assert!(!encoded.is_empty(), "encoded output should not be empty");
 
-        let decoded = huffman.decode(&encoded);
+        let decoded = huffman.decode(&encoded).unwrap();
         assert_eq!(decoded, input);
     }
 
     #[test]
     fn single_character_input() {
         let input = "aaaaaa".to_string();
-        let mut huffman = Huffman::new(&input.clone());
+        let mut huffman = Huffman::try_new(&input).unwrap();
 
-        let encoded = huffman.encode();
-        let decoded = huffman.decode(&encoded);
+        let encoded = huffman.encode().unwrap();
+        let decoded = huffman.decode(&encoded).unwrap();
 
         assert_eq!(decoded, input);
     }
 
+    #[test]
+    fn decode_stored_recovers_input_without_length() {
+        let mut huffman = Huffman::try_new("stored bits carry their own length").unwrap();
+        assert_eq!(huffman.decode_stored().unwrap(), b"");
+
+        let encoded = huffman.encode().unwrap();
+        assert_eq!(huffman.encoded(), &encoded);
+        assert_eq!(huffman.decode_stored().unwrap(), huffman.input());
+    }
+
+    #[test]
+    fn every_byte_value_roundtrip() {
+        let mut input: Vec<u8> = (0..=255).collect();
+        input.extend([0, 0, 0xff, 0xff, 0xff]);
+        let huffman = Huffman::from_bytes(input.clone());
+
+        assert_eq!(huffman.table().len(), 256);
+        let encoded = huffman.encode_bits().unwrap();
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn canonicalize_keeps_lengths_and_prefix_property() {
+        let input = b"canonical codes depend only on the code lengths".to_vec();
+        let mut huffman = Huffman::from_bytes(input.clone());
+        let lengths = huffman.canonical_table();
+        let average = huffman.average_code_length();
+
+        huffman.canonicalize();
+
+        assert_eq!(huffman.canonical_table(), lengths);
+        assert_eq!(huffman.average_code_length(), average);
+        for (byte, code) in huffman.table() {
+            assert!(huffman.table().iter().all(|(other, other_code)| {
+                other == byte || !other_code.starts_with(code)
+            }));
+        }
+        assert_eq!(huffman.table(), Huffman::from_code_lengths(&lengths).unwrap().table());
+
+        let encoded = huffman.encode_bytes(&input).unwrap();
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), input);
+        assert_eq!(huffman.decode_lut(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn canonicalize_single_symbol_still_decodes() {
+        let mut huffman = Huffman::from_bytes(b"zzzz".to_vec());
+        huffman.canonicalize();
+
+        assert_eq!(huffman.canonical_table(), [(b'z', 1)]);
+        let encoded = huffman.encode_bytes(b"zzzz").unwrap();
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), b"zzzz");
+        assert_eq!(huffman.decode_lut(&encoded).unwrap(), b"zzzz");
+    }
+
+    #[test]
+    fn compression_ratio_after_encode() {
+        let mut huffman = Huffman::from_bytes(b"aaaaaaaaaaaaaaaaaaab".repeat(20));
+        assert_eq!(huffman.compression_ratio(), None);
+
+        huffman.encode().unwrap();
+        let ratio = huffman.compression_ratio().unwrap();
+        assert!(ratio < 0.25, "ratio {ratio}");
+
+        assert_eq!(Huffman::from_bytes(Vec::new()).compression_ratio(), None);
+    }
+
+    #[test]
+    fn single_symbol_codec_from_code_lengths_roundtrip() {
+        let huffman = Huffman::from_code_lengths(&[(b'q', 1)]).unwrap();
+        let encoded = huffman.encode_bytes(b"qqqq").unwrap();
+
+        assert_eq!(encoded.len(), 4);
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), b"qqqq");
+        assert_eq!(
+            Huffman::from_code_lengths(&[(b'q', 2)]).unwrap_err(),
+            HuffmanError::InvalidCodeLengths
+        );
+    }
+
+    #[test]
+    fn single_symbol_codec_from_serialized_table_roundtrip() {
+        let huffman = Huffman::try_new("aaaaa").unwrap();
+        let table = HuffmanTable::new(
+            huffman
+                .table()
+                .iter()
+                .map(|(byte, code)| (code.iter().by_vals().collect(), *byte))
+                .collect(),
+        );
+        let bits = huffman.encode_bits().unwrap();
+
+        let fresh = Huffman::from_serialized_table(&table).unwrap();
+        assert_eq!(fresh.decode_checked(&bits).unwrap(), b"aaaaa");
+    }
+
+    #[test]
+    fn lenient_decoders_only_ignore_trailing_bits() {
+        let mut huffman = Huffman::from_bytes(b"aaaaaaaaaabcde".to_vec());
+        let mut bits = huffman.encode_bytes(b"ab").unwrap();
+        bits.push(false);
+        let packed: BitVec<u8> = bits.iter().by_vals().collect();
+        assert_eq!(huffman.decode(&bits).unwrap(), "ab");
+        assert_eq!(huffman.decode_bits(&packed).unwrap(), b"ab");
+
+        let table = HashMap::from([(b'a', bitvec![0]), (b'b', bitvec![1, 0])]);
+        let mut broken = Huffman::from_table(table).unwrap();
+        assert_eq!(broken.decode(&bitvec![0, 1, 0]), Err(HuffmanError::IncompleteTree));
+        assert_eq!(
+            broken.decode_bits(&bitvec![u8, Lsb0; 0]),
+            Err(HuffmanError::IncompleteTree)
+        );
+    }
+
+    #[test]
+    fn single_symbol_gets_one_bit_code() {
+        let mut huffman = Huffman::try_new("aaaaa").unwrap();
+        assert_eq!(huffman.table()[&b'a'], bitvec![0]);
+
+        let encoded = huffman.encode().unwrap();
+        assert_eq!(encoded.len(), 5);
+        assert_eq!(huffman.decode(&encoded).unwrap(), "aaaaa");
+    }
+
+    #[test]
+    fn long_single_symbol_buffer_roundtrip() {
+        let input = vec![b'z'; 10_000];
+        let huffman = Huffman::from_bytes(input.clone());
+
+        let encoded = huffman.encode_bytes(&input).unwrap();
+        assert_eq!(encoded.len(), 10_000);
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), input);
+    }
+
     #[test]
     fn empty_input() {
-        let input = "".to_string();
-        let mut huffman = Huffman::new(&input.clone());
+        assert_eq!(Huffman::try_new("").unwrap_err(), HuffmanError::EmptyInput);
 
-        let encoded = huffman.encode();
-        let decoded = huffman.decode(&encoded);
+        let mut huffman = Huffman::from_bytes(Vec::new());
+        let encoded = huffman.encode().unwrap();
+        let decoded = huffman.decode(&encoded).unwrap();
 
         assert!(encoded.is_empty());
-        assert_eq!(decoded, input);
+        assert_eq!(decoded, "");
     }
 
     #[test]
     fn all_unique_characters() {
         let input = "abcdefg".to_string();
-        let mut huffman = Huffman::new(&input.clone());
+        let mut huffman = Huffman::try_new(&input).unwrap();
 
-        let encoded = huffman.encode();
-        let decoded = huffman.decode(&encoded);
+        let encoded = huffman.encode().unwrap();
+        let decoded = huffman.decode(&encoded).unwrap();
 
         assert_eq!(decoded, input);
     }
@@ -147,7 +959,7 @@ mod tests {
     #[test]
     fn table_contains_all_input_bytes() {
         let input = "mississippi".to_string();
-        let huffman = Huffman::new(&input.clone());
+        let huffman = Huffman::try_new(&input).unwrap();
 
         for byte in input.bytes() {
             assert!(
@@ -161,7 +973,7 @@ mod tests {
     #[test]
     fn no_empty_codes_in_table() {
         let input = "hello".to_string();
-        let huffman = Huffman::new(&input);
+        let huffman = Huffman::try_new(&input).unwrap();
 
         for (byte, code) in &huffman.table {
             assert!(
@@ -176,13 +988,525 @@ mod tests {
     fn encoding_is_deterministic() {
         let input = "banana".to_string();
 
-        let mut h1 = Huffman::new(&input.clone());
-        let mut h2 = Huffman::new(&input.clone());
+        let mut h1 = Huffman::try_new(&input).unwrap();
+        let mut h2 = Huffman::try_new(&input).unwrap();
 
-        let b1 = h1.encode();
-        let b2 = h2.encode();
+        let b1 = h1.encode().unwrap();
+        let b2 = h2.encode().unwrap();
 
         assert_eq!(b1, b2);
     }
 
+    #[test]
+    fn two_symbol_tree_shape() {
+        let huffman = Huffman::try_new("aab").unwrap();
+        let root = huffman.root();
+
+        assert_eq!(root.count, 3);
+        assert_eq!(root.height(), 1);
+
+        let left = root.left.as_ref().unwrap();
+        let right = root.right.as_ref().unwrap();
+        assert_eq!((left.byte, left.count), (Some(b'b'), 1));
+        assert_eq!((right.byte, right.count), (Some(b'a'), 2));
+
+        assert_eq!(huffman.table[&b'b'], bitvec![0]);
+        assert_eq!(huffman.table[&b'a'], bitvec![1]);
+    }
+
+    #[test]
+    fn tree_decode_matches_table_decode() {
+        let input = "the quick brown fox jumps over the lazy dog".to_string();
+        let mut huffman = Huffman::try_new(&input).unwrap();
+        let encoded = huffman.encode().unwrap();
+
+        // Reference decoder: accumulate bits until they match a table entry
+        let mut current = bitvec![];
+        let mut table_decoded = String::new();
+        for bit in encoded.iter().by_vals() {
+            current.push(bit);
+            if let Some((byte, _)) = huffman.table.iter().find(|entry| *entry.1 == current) {
+                table_decoded.push(*byte as char);
+                current.clear();
+            }
+        }
+
+        assert_eq!(huffman.decode(&encoded).unwrap(), table_decoded);
+        assert_eq!(table_decoded, input);
+    }
+
+    #[test]
+    fn quantized_counts_roundtrip_and_stay_near_optimal() {
+        let input = "it was the best of times, it was the worst of times, \
+            it was the age of wisdom, it was the age of foolishness. "
+            .repeat(200)
+            .into_bytes();
+
+        let mut exact = Huffman::from_bytes(input.clone());
+        let mut quantized = Huffman::with_quantized_counts(input.clone(), 255);
+
+        let exact_bits = exact.encode().unwrap();
+        let quantized_bits = quantized.encode().unwrap();
+
+        let decoded = quantized.decode(&quantized_bits).unwrap();
+        assert_eq!(decoded.into_bytes(), input);
+        assert_eq!(quantized.table.len(), exact.table.len());
+
+        // Within 2% of the optimal code built from exact counts
+        assert!(quantized_bits.len() as f64 <= exact_bits.len() as f64 * 1.02);
+    }
+
+    #[test]
+    fn export_rust_table_lists_every_symbol() {
+        let huffman = Huffman::try_new("abracadabra").unwrap();
+        let source = huffman.export_rust_table("CODES");
+
+        assert!(source.starts_with("pub const CODES: [(u8, u8, &[u8]); 5] = [\n"));
+        assert!(source.ends_with("];\n"));
+
+        // Every entry line parses back to the code in the table
+        let entries: Vec<&str> = source
+            .lines()
+            .filter(|line| line.starts_with("    ("))
+            .collect();
+        assert_eq!(entries.len(), huffman.table.len());
+        for line in entries {
+            let fields: Vec<&str> = line
+                .trim_start_matches("    (")
+                .trim_end_matches("]),")
+                .split(", ")
+                .collect();
+            let byte = u8::from_str_radix(fields[0].trim_start_matches("0x"), 16).unwrap();
+            let len: usize = fields[1].parse().unwrap();
+            let packed: Vec<u8> = fields[2..]
+                .iter()
+                .map(|field| field.trim_start_matches("&[").trim_start_matches("0x"))
+                .map(|field| u8::from_str_radix(field, 16).unwrap())
+                .collect();
+
+            let code = &huffman.table[&byte];
+            assert_eq!(len, code.len());
+            assert_eq!(packed, pack_bits(code));
+        }
+    }
+
+    #[test]
+    fn encode_without_table_is_an_error() {
+        let mut huffman = Huffman {
+            input: b"abc".to_vec(),
+            ..Default::default()
+        };
+
+        assert_eq!(huffman.encode(), Err(HuffmanError::NoTable));
+    }
+
+    fn average_length(huffman: &Huffman) -> f64 {
+        let total_bits: usize = huffman.input.iter().map(|byte| huffman.table[byte].len()).sum();
+        total_bits as f64 / huffman.input.len() as f64
+    }
+
+    fn assert_prefix_free(table: &HashMap<u8, BitVec>) {
+        for (a, code_a) in table {
+            for (b, code_b) in table {
+                if a != b {
+                    assert!(!code_b.starts_with(code_a), "{} is a prefix of {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn code_builders_produce_prefix_free_codes() {
+        let input = b"she sells sea shells by the sea shore".to_vec();
+
+        let mut standard = Huffman::with_builder(input.clone(), &StandardHuffman);
+        let mut balanced = Huffman::with_builder(input.clone(), &BalancedHuffman);
+        let mut shannon_fano = Huffman::with_builder(input.clone(), &ShannonFano);
+
+        for huffman in [&mut standard, &mut balanced, &mut shannon_fano] {
+            assert_prefix_free(&huffman.table);
+            let encoded = huffman.encode().unwrap();
+            assert_eq!(huffman.decode(&encoded).unwrap().into_bytes(), input);
+        }
+    }
+
+    #[test]
+    fn huffman_is_no_longer_than_shannon_fano() {
+        // Shannon-Fano is strictly worse on this distribution
+        let input = [
+            vec![b'a'; 35],
+            vec![b'b'; 17],
+            vec![b'c'; 17],
+            vec![b'd'; 16],
+            vec![b'e'; 15],
+        ]
+        .concat();
+
+        let standard = Huffman::with_builder(input.clone(), &StandardHuffman);
+        let shannon_fano = Huffman::with_builder(input, &ShannonFano);
+
+        assert!(average_length(&standard) < average_length(&shannon_fano));
+    }
+
+    #[test]
+    fn from_code_lengths_roundtrip() {
+        let lengths = [(b'a', 1), (b'b', 2), (b'c', 3), (b'd', 3)];
+        let mut huffman = Huffman::from_code_lengths(&lengths).unwrap();
+
+        assert_eq!(huffman.table[&b'a'], bitvec![0]);
+        assert_eq!(huffman.table[&b'b'], bitvec![1, 0]);
+        assert_eq!(huffman.table[&b'c'], bitvec![1, 1, 0]);
+        assert_eq!(huffman.table[&b'd'], bitvec![1, 1, 1]);
+
+        let encoded = huffman.encode_bytes(b"abacabad").unwrap();
+        assert_eq!(encoded.len(), 14);
+        assert_eq!(huffman.decode(&encoded).unwrap(), "abacabad");
+
+        assert_eq!(
+            huffman.encode_bytes(b"abe"),
+            Err(HuffmanError::UnexpectedSymbol(b'e'))
+        );
+    }
+
+    #[test]
+    fn from_code_lengths_rejects_oversubscribed_set() {
+        // Three 1-bit codes cannot be prefix-free
+        let lengths = [(b'a', 1), (b'b', 1), (b'c', 1)];
+
+        assert_eq!(
+            Huffman::from_code_lengths(&lengths).unwrap_err(),
+            HuffmanError::InvalidCodeLengths
+        );
+        assert_eq!(
+            Huffman::from_code_lengths(&[(b'a', 0)]).unwrap_err(),
+            HuffmanError::InvalidCodeLengths
+        );
+    }
+
+    #[test]
+    fn truncated_stream_reports_bit_offset() {
+        let huffman = Huffman::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2)]).unwrap();
+        // "abc" is 0 10 11; cut it inside the code for 'c'
+        let encoded = huffman.encode_bytes(b"abc").unwrap();
+        let truncated = &encoded[..4];
+
+        assert_eq!(
+            huffman.decode_checked(truncated),
+            Err(HuffmanError::IncompleteCode { at_bit: 4 })
+        );
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn warmed_lut_decode_matches_tree_decode() {
+        // Skewed enough that some codes are longer than the lookup window
+        let input: Vec<u8> = (0..14u8)
+            .flat_map(|i| std::iter::repeat_n(b'a' + i, 1 << i))
+            .collect();
+        let mut huffman = Huffman::from_bytes(input.clone());
+        let encoded = huffman.encode().unwrap();
+        assert!(huffman.table.values().any(|code| code.len() > lut::MAX_LUT_BITS));
+
+        let expected = huffman.decode_checked(&encoded).unwrap();
+        huffman.warm();
+
+        assert_eq!(huffman.decode_lut(&encoded).unwrap(), expected);
+        assert_eq!(expected, input);
+    }
+
+    #[test]
+    fn lut_decode_reports_incomplete_code() {
+        let mut huffman = Huffman::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2)]).unwrap();
+        let encoded = huffman.encode_bytes(b"abc").unwrap();
+
+        assert_eq!(
+            huffman.decode_lut(&encoded[..4]),
+            Err(HuffmanError::IncompleteCode { at_bit: 4 })
+        );
+    }
+
+    #[test]
+    fn decode_packed_in_both_orders() {
+        let huffman = Huffman::from_bytes(b"packed bits, either order".to_vec());
+        let encoded = huffman.encode_bytes(b"packed bits").unwrap();
+
+        let lsb = pack_bits(&encoded);
+        let msb: BitVec<u8, Msb0> = encoded.iter().by_vals().collect();
+        let msb = msb.into_vec();
+
+        assert_eq!(
+            huffman.decode_packed(&lsb, encoded.len(), io::BitOrder::LsbFirst).unwrap(),
+            b"packed bits"
+        );
+        assert_eq!(
+            huffman.decode_packed(&msb, encoded.len(), io::BitOrder::MsbFirst).unwrap(),
+            b"packed bits"
+        );
+        assert_eq!(
+            huffman.decode_packed(&lsb, lsb.len() * 8 + 1, io::BitOrder::LsbFirst),
+            Err(HuffmanError::Truncated)
+        );
+    }
+
+    #[test]
+    fn deep_tree_table_generation() {
+        // Fibonacci counts give the deepest tree a byte alphabet can get
+        // without overflowing the count total: one symbol per level
+        let mut counts = HashMap::new();
+        let (mut a, mut b) = (1usize, 1usize);
+        for symbol in 0..90u8 {
+            counts.insert(symbol, a);
+            (a, b) = (b, a + b);
+        }
+
+        let huffman = Huffman::from_counts(Vec::new(), counts);
+
+        assert_eq!(huffman.table().len(), 90);
+        assert_eq!(huffman.table().values().map(|code| code.len()).max(), Some(89));
+        assert_prefix_free(huffman.table());
+    }
+
+    #[test]
+    fn prefix_free_check_passes_for_built_tables() {
+        let huffman = Huffman::from_bytes(b"a correct build never trips the check".to_vec());
+
+        assert_prefix_free(huffman.table());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "conflicts with an existing code")]
+    fn prefix_free_check_catches_conflicts() {
+        let tree = Huffman::from_bytes(b"aab".to_vec());
+        let mut table = HashMap::from([(b'z', bitvec![0])]);
+
+        Huffman::make_table(&mut table, tree.root(), &mut bitvec![]);
+    }
+
+    #[test]
+    fn code_bits_match_table_codes() {
+        let huffman = Huffman::from_bytes(b"abbcccddddeeeee".to_vec());
+
+        for (byte, code) in huffman.table() {
+            let bits = huffman.code_bits(*byte).unwrap();
+            assert!(bits.iter().by_vals().eq(code.iter().by_vals()));
+        }
+        assert_eq!(huffman.code_bits(b'z'), None);
+    }
+
+    #[test]
+    fn decode_each_matches_eager_decode() {
+        let input = b"sum every decoded byte".to_vec();
+        let mut huffman = Huffman::from_bytes(input.clone());
+        let encoded = huffman.encode().unwrap();
+
+        let mut sum = 0u64;
+        huffman.decode_each(&encoded, |byte| sum += u64::from(byte)).unwrap();
+
+        let eager: u64 = huffman.decode_checked(&encoded).unwrap().iter().map(|b| u64::from(*b)).sum();
+        assert_eq!(sum, eager);
+    }
+
+    #[test]
+    fn bit_orders_are_each_self_consistent() {
+        let huffman = Huffman::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2)]).unwrap();
+        let input = b"abcabbacca";
+        let encoded = huffman.encode_bytes(input).unwrap();
+
+        let mut packed = Vec::new();
+        for order in [io::BitOrder::LsbFirst, io::BitOrder::MsbFirst] {
+            let bytes = io::pack_bits_ordered(&encoded, order);
+            assert_eq!(huffman.decode_packed(&bytes, encoded.len(), order).unwrap(), input);
+            packed.push(bytes);
+        }
+        assert_ne!(packed[0], packed[1]);
+        assert_eq!(packed[0], pack_bits(&encoded));
+
+        // "abc" is 0 10 11
+        let tiny = huffman.encode_bytes(b"abc").unwrap();
+        assert_eq!(io::pack_bits_ordered(&tiny, io::BitOrder::LsbFirst), [0b0001_1010]);
+        assert_eq!(io::pack_bits_ordered(&tiny, io::BitOrder::MsbFirst), [0b0101_1000]);
+    }
+
+    #[test]
+    fn average_code_length_is_within_one_bit_of_entropy() {
+        let input = b"it was the best of times, it was the worst of times".to_vec();
+        let huffman = Huffman::from_bytes(input.clone());
+
+        let entropy = HuffmanNode::entropy(&HuffmanNode::count_bytes(&input));
+
+        let average = huffman.average_code_length();
+        assert!(entropy <= average && average < entropy + 1.0);
+        let encoded_len = huffman.encode_bytes(&input).unwrap().len();
+        assert!((average - encoded_len as f64 / input.len() as f64).abs() < 1e-12);
+        assert!(huffman.is_within_one_bit_of_entropy());
+        assert!(Huffman::from_bytes(b"zzzz".to_vec()).is_within_one_bit_of_entropy());
+    }
+
+    #[test]
+    fn zero_counts_get_no_code() {
+        let counts = HashMap::from([(b'a', 5), (b'b', 0), (b'c', 2)]);
+
+        let huffman = Huffman::with_counts(counts.clone(), false).unwrap();
+        assert_eq!(huffman.table().len(), 2);
+        assert!(!huffman.table().contains_key(&b'b'));
+
+        let strict = Huffman::with_counts(counts, true);
+        assert_eq!(strict.unwrap_err(), HuffmanError::ZeroFrequency(b'b'));
+    }
+
+    #[test]
+    fn presized_encode_matches_growing_encode() {
+        let input = b"presize the output before the loop".repeat(20);
+        let huffman = Huffman::from_bytes(input.clone());
+
+        let mut grown = BitVec::new();
+        huffman.encode_into(&input, &mut grown).unwrap();
+        let presized = huffman.encode_bytes(&input).unwrap();
+
+        assert_eq!(presized, grown);
+        assert_eq!(huffman.total_bits(&input).unwrap(), grown.len());
+        assert_eq!(huffman.total_bits(b"!"), Err(HuffmanError::UnexpectedSymbol(b'!')));
+    }
+
+    #[test]
+    fn fingerprint_tracks_codebook() {
+        let input = b"fingerprint the trained codebook".to_vec();
+        let first = Huffman::from_bytes(input.clone());
+        let again = Huffman::from_bytes(input.clone());
+        let other = Huffman::from_bytes(b"a different training set".to_vec());
+
+        assert_eq!(first.fingerprint(), again.fingerprint());
+        assert_ne!(first.fingerprint(), other.fingerprint());
+
+        // One 0xff gives the escape leaf the same weight as 0xff's own, so
+        // the tables match, but 0xff is then written as a literal
+        let marked = [input, vec![0xff]].concat();
+        let plain = CodecBuilder::new().build(marked.clone()).unwrap();
+        let reserved = CodecBuilder::new().reserve(&[0xff]).build(marked).unwrap();
+        assert_eq!(plain.table(), reserved.table());
+        assert_ne!(plain.fingerprint(), reserved.fingerprint());
+    }
+
+    #[test]
+    fn decode_until_exhausted_without_bit_count() {
+        // a = 1 and four 3-bit codes under 0, so "0" alone never completes one
+        let huffman = Huffman::from_bytes(b"aaaaaaaaaabcde".to_vec());
+
+        let aligned = pack_bits(&huffman.encode_bytes(b"aaaaab").unwrap());
+        assert_eq!(aligned.len(), 1);
+        assert_eq!(huffman.decode_until_exhausted(&aligned).unwrap(), b"aaaaab");
+
+        let padded = pack_bits(&huffman.encode_bytes(b"aaaab").unwrap());
+        assert_eq!(huffman.decode_until_exhausted(&padded).unwrap(), b"aaaab");
+
+        // "aaa", a 3-bit code, then the first 2 bits ("01") of another
+        let (&under_01, _) = huffman
+            .table()
+            .iter()
+            .find(|(_, code)| code.len() == 3 && code[1])
+            .unwrap();
+        let mut cut = huffman.encode_bytes(&[b'a', b'a', b'a', under_01, under_01]).unwrap();
+        cut.truncate(8);
+        assert_eq!(
+            huffman.decode_until_exhausted(&pack_bits(&cut)),
+            Err(HuffmanError::IncompleteCode { at_bit: 8 })
+        );
+    }
+
+    #[test]
+    fn encode_bits_packs_to_whole_bytes() {
+        let huffman = Huffman::from_bytes(b"packed bits, not one char per bit".repeat(10));
+        let bits = huffman.encode_bits().unwrap();
+
+        assert_eq!(bits.len(), huffman.total_bits(&huffman.input).unwrap());
+        assert_eq!(bits.as_raw_slice().len(), bits.len().div_ceil(8));
+        assert!(bits.as_raw_slice().len() < huffman.input.len());
+        assert_eq!(huffman.decode_checked(&bits).unwrap(), huffman.input);
+    }
+
+    #[test]
+    fn counts_to_csv_lists_every_symbol() {
+        let huffman = Huffman::from_bytes(b"aaaa,,\nb".to_vec());
+        let csv = huffman.counts_to_csv();
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next(), Some("byte,char,count,code,code_length"));
+        let rows: Vec<&str> = lines.collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].starts_with("97,a,4,"));
+        assert!(rows[1].starts_with("44,\",\",2,"));
+        assert!(rows[2].starts_with("10,\\n,1,"));
+        assert!(rows[3].starts_with("98,b,1,"));
+
+        for row in rows {
+            let byte: u8 = row.split(',').next().unwrap().parse().unwrap();
+            let fields: Vec<&str> = row.rsplitn(3, ',').collect();
+            let code = huffman.code_bits(byte).unwrap();
+            assert_eq!(fields[0], code.len().to_string());
+            assert_eq!(fields[1].len(), code.len());
+        }
+    }
+
+    #[test]
+    fn dictionary_symbols_expand_to_phrases() {
+        let original = b"hello world, hello there, hello!";
+        let phrase = b"hello";
+        let dict = HashMap::from([(0x01, phrase.to_vec())]);
+
+        // Replace each occurrence of the phrase with its symbol
+        let mut symbols = Vec::new();
+        let mut rest = &original[..];
+        while !rest.is_empty() {
+            if rest.starts_with(phrase) {
+                symbols.push(0x01);
+                rest = &rest[phrase.len()..];
+            } else {
+                symbols.push(rest[0]);
+                rest = &rest[1..];
+            }
+        }
+
+        let huffman = Huffman::from_bytes(symbols.clone());
+        let encoded = huffman.encode_bytes(&symbols).unwrap();
+
+        assert_eq!(huffman.decode_with_dictionary(&encoded, &dict).unwrap(), original);
+        assert_eq!(huffman.decode_checked(&encoded).unwrap(), symbols);
+    }
+
+    #[test]
+    fn balanced_tie_break_keeps_uniform_codes_flat() {
+        let uniform = Huffman::with_builder(b"abcdefgh".to_vec(), &BalancedHuffman);
+        assert_eq!(uniform.table.len(), 8);
+        assert!(uniform.table.values().all(|code| code.len() == 3));
+
+        // b + e and then h + (b e) give two 4-count nodes of different
+        // heights, d + g being the other; i (3) is merged with the taller
+        // one by the standard builder, pushing b and e to 5 bits
+        let input = b"aaabcccddefffffgghhiii".to_vec();
+        let balanced = Huffman::with_builder(input.clone(), &BalancedHuffman);
+        let standard = Huffman::with_builder(input, &StandardHuffman);
+
+        let max_len = |huffman: &Huffman| huffman.table.values().map(|code| code.len()).max();
+        assert_eq!(standard.table[&b'b'].len(), 5);
+        assert_eq!(max_len(&standard), Some(5));
+        assert_eq!(max_len(&balanced), Some(4));
+        assert_eq!(average_length(&balanced), average_length(&standard));
+    }
+
+    #[test]
+    fn incomplete_tree_is_rejected_before_decoding() {
+        // Lengths 1 and 2 leave the code "11" unused
+        assert_eq!(
+            Huffman::from_code_lengths(&[(b'a', 1), (b'b', 2)]).unwrap_err(),
+            HuffmanError::InvalidCodeLengths
+        );
+        let table = HashMap::from([(b'a', bitvec![0]), (b'b', bitvec![1, 0])]);
+        let huffman = Huffman::from_table(table).unwrap();
+        assert!(!huffman.root().is_complete());
+
+        let encoded = huffman.encode_bytes(b"aba").unwrap();
+        assert_eq!(huffman.decode_checked(&encoded), Err(HuffmanError::IncompleteTree));
+        assert_eq!(huffman.decode_until_exhausted(&[0]), Err(HuffmanError::IncompleteTree));
+    }
 }
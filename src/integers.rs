@@ -0,0 +1,103 @@
+use crate::archive::{compress, decompress};
+use crate::huffman::HuffmanError;
+
+
+/// Compresses signed integers by zigzag-mapping them to unsigned values
+/// (so small magnitudes of either sign become small numbers), splitting
+/// them into little-endian bytes and Huffman-coding those bytes.
+pub fn compress_i32(values: &[i32]) -> Vec<u8> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| zigzag_encode_i32(*value).to_le_bytes())
+        .collect();
+
+    compress(&bytes).0
+}
+
+pub fn decompress_i32(archive: &[u8]) -> Result<Vec<i32>, HuffmanError> {
+    let bytes = decompress(archive)?;
+    if bytes.len() % 4 != 0 {
+        return Err(HuffmanError::InvalidLength);
+    }
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| zigzag_decode_i32(u32::from_le_bytes(chunk.try_into().unwrap())))
+        .collect())
+}
+
+pub fn compress_i64(values: &[i64]) -> Vec<u8> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| zigzag_encode_i64(*value).to_le_bytes())
+        .collect();
+
+    compress(&bytes).0
+}
+
+pub fn decompress_i64(archive: &[u8]) -> Result<Vec<i64>, HuffmanError> {
+    let bytes = decompress(archive)?;
+    if bytes.len() % 8 != 0 {
+        return Err(HuffmanError::InvalidLength);
+    }
+
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|chunk| zigzag_decode_i64(u64::from_le_bytes(chunk.try_into().unwrap())))
+        .collect())
+}
+
+fn zigzag_encode_i32(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn zigzag_decode_i32(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+fn zigzag_encode_i64(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode_i64(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zigzag_maps_small_magnitudes_to_small_values() {
+        assert_eq!(zigzag_encode_i32(0), 0);
+        assert_eq!(zigzag_encode_i32(-1), 1);
+        assert_eq!(zigzag_encode_i32(1), 2);
+        assert_eq!(zigzag_encode_i32(-2), 3);
+        assert_eq!(zigzag_decode_i32(zigzag_encode_i32(i32::MIN)), i32::MIN);
+        assert_eq!(zigzag_decode_i32(zigzag_encode_i32(i32::MAX)), i32::MAX);
+    }
+
+    #[test]
+    fn i32_roundtrip_with_negatives() {
+        let values = vec![0, -1, 1, -2, 2, -300, 300, i32::MIN, i32::MAX, -7, -7, -7];
+        let archive = compress_i32(&values);
+
+        assert_eq!(decompress_i32(&archive).unwrap(), values);
+    }
+
+    #[test]
+    fn i64_roundtrip_with_negatives() {
+        let values = vec![0, -1, 1, -5_000_000_000, 5_000_000_000, i64::MIN, i64::MAX];
+        let archive = compress_i64(&values);
+
+        assert_eq!(decompress_i64(&archive).unwrap(), values);
+    }
+
+    #[test]
+    fn rejects_payload_of_wrong_width() {
+        let archive = compress(&[1, 2, 3]).0;
+
+        assert_eq!(decompress_i32(&archive), Err(HuffmanError::InvalidLength));
+    }
+}
@@ -1,50 +1,311 @@
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::PathBuf;
 use std::fs::{self, File};
 
 use bitvec::vec::BitVec;
 
+use crate::archive::{read_table, write_table, ByteReader};
+use crate::huffman::HuffmanTable;
+
+
+/// Order in which bits are packed into each byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    /// First bit in the least significant position, as `pack_bits` writes.
+    #[default]
+    LsbFirst,
+    /// First bit in the most significant position.
+    MsbFirst,
+}
+
+/// Iterator over the first `bit_count` bits of a byte slice in a given
+/// order, reading straight from the bytes without building a `BitVec`.
+#[derive(Debug, Clone)]
+pub struct BitIter<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    bit_count: usize,
+    order: BitOrder,
+}
+
+impl<'a> BitIter<'a> {
+    /// `bit_count` is capped at the number of bits in `bytes`.
+    pub fn new(bytes: &'a [u8], bit_count: usize, order: BitOrder) -> Self {
+        BitIter { bytes, pos: 0, bit_count: bit_count.min(bytes.len() * 8), order }
+    }
+}
+
+impl Iterator for BitIter<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.pos >= self.bit_count {
+            return None;
+        }
+
+        let byte = self.bytes[self.pos / 8];
+        let shift = match self.order {
+            BitOrder::LsbFirst => self.pos % 8,
+            BitOrder::MsbFirst => 7 - self.pos % 8,
+        };
+        self.pos += 1;
+
+        Some(byte >> shift & 1 == 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.bit_count - self.pos;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for BitIter<'_> {}
 
 pub fn read_string_file(file_path: PathBuf) -> Result<String, std::io::Error> {
     fs::read_to_string(file_path)
 }
 
+/// Reads a file as raw bytes, so binary files without valid UTF-8 work.
+pub fn read_bytes_file(file_path: PathBuf) -> Result<Vec<u8>, std::io::Error> {
+    fs::read(file_path)
+}
+
 pub fn write_string_to_file(file_path: PathBuf, contents: &String) -> Result<(), std::io::Error> {
     fs::write(file_path, contents)
 }
 
-pub fn read_huffman_file(file_path: PathBuf) -> Result<BitVec<u8>, std::io::Error> {
-    let mut file = File::open(file_path)?;
+/// Reads a file written by `write_bits_to_file`: the code table, then
+/// the bits, along with the number of payload bytes they occupied. Any
+/// bytes after those (padding, another concatenated file) are ignored;
+/// compare the consumed length with the file size to detect them. A
+/// malformed table fails with `InvalidData`.
+pub fn read_huffman_file(file_path: PathBuf)
+    -> Result<(HuffmanTable, BitVec<u8>, usize), std::io::Error> {
+    let bytes = fs::read(file_path)?;
+    let mut reader = ByteReader::new(&bytes);
 
-    let mut count_bytes = [0u8; 8];
-    file.read_exact(&mut count_bytes)?;
-    let bit_count = u64::from_le_bytes(count_bytes) as usize;
+    let table = HuffmanTable::new(read_table(&mut reader)?);
+    let bit_count = reader.take_u64()? as usize;
 
-    let mut bytes = Vec::new();
-    file.read_to_end(&mut bytes)?;
+    let consumed = bit_count.div_ceil(8);
+    let payload = &bytes[reader.pos..];
+    if payload.len() < consumed {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "payload is shorter than its bit count",
+        ));
+    }
 
-    let mut bitvec = BitVec::from_vec(bytes);
+    let mut bitvec = BitVec::from_slice(&payload[..consumed]);
     bitvec.truncate(bit_count);
 
-    Ok(bitvec)
+    Ok((table, bitvec, consumed))
 }
 
-pub fn write_bits_to_file(file_path: PathBuf, contents: &BitVec) -> Result<(), std::io::Error> {
-    let mut bytes = Vec::new();
+/// Writes `table` in the archive table layout (a u16 entry count, then
+/// each byte, its code length and the packed code) so the file decodes
+/// on its own (see `Huffman::from_serialized_table`), followed by the bit
+/// count and `contents` (e.g. from `Huffman::encode_bits`) packed
+/// LSB-first by `pack_bitvec`.
+///
+/// This is a bare table and payload, not a `HUFF` archive, so
+/// `decompress_file` can't open it; use a `.bits` name rather than
+/// `.huff` and read it back with `read_huffman_file`.
+pub fn write_bits_to_file(file_path: PathBuf, table: &HashMap<u8, BitVec>,
+    contents: &BitVec<u8>) -> Result<(), std::io::Error> {
+    let mut header = Vec::new();
+    write_table(&mut header, table);
+    header.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+
+    let (bytes, _) = pack_bitvec(contents, BitOrder::LsbFirst);
+
     let mut file = File::create(file_path)?;
+    file.write_all(&header)?;
+    file.write_all(&bytes)
+}
 
-    let bit_count = contents.len();
-    file.write_all(&bit_count.to_le_bytes())?;
-    
-    for chunk in contents.chunks(8) {
-        let mut byte = 0u8;
-        for (i, bit) in chunk.iter().enumerate() {
-            if *bit {
-                byte |= 1 << i;
-            }
+/// Packs bits LSB-first; the last byte is zero-padded.
+pub fn pack_bits(contents: &BitVec) -> Vec<u8> {
+    pack_iter(contents.iter().by_vals(), contents.len(), BitOrder::LsbFirst)
+}
+
+/// Packs bits into bytes in the given order; `LsbFirst` matches
+/// `pack_bits`. The last byte is zero-padded.
+pub fn pack_bits_ordered(contents: &BitVec, order: BitOrder) -> Vec<u8> {
+    pack_iter(contents.iter().by_vals(), contents.len(), order)
+}
+
+/// Packs `bits` in the given order, returning the bytes and the bit
+/// count needed to unpack them; the last byte is zero-padded. Inverse of
+/// `unpack_bitvec`.
+pub fn pack_bitvec(bits: &BitVec<u8>, order: BitOrder) -> (Vec<u8>, usize) {
+    (pack_iter(bits.iter().by_vals(), bits.len(), order), bits.len())
+}
+
+/// Unpacks the first `bit_count` bits of `bytes`, read in the given
+/// order. `bit_count` is capped at the number of bits in `bytes`.
+pub fn unpack_bitvec(bytes: &[u8], bit_count: usize, order: BitOrder) -> BitVec<u8> {
+    BitIter::new(bytes, bit_count, order).collect()
+}
+
+fn pack_iter(bits: impl Iterator<Item = bool>, len: usize, order: BitOrder) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len.div_ceil(8));
+    let mut packer = BitPacker::new(order);
+    for bit in bits {
+        packer.push(bit, &mut bytes);
+    }
+    packer.finish(&mut bytes);
+
+    bytes
+}
+
+/// Packs bits into bytes as they arrive, for writers that stream codes
+/// out rather than collect them first. Every packer in the crate goes
+/// through this one.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BitPacker {
+    order: BitOrder,
+    current: u8,
+    filled: u8,
+}
+
+impl BitPacker {
+    pub fn new(order: BitOrder) -> Self {
+        BitPacker { order, current: 0, filled: 0 }
+    }
+
+    /// Adds a bit, appending the byte to `out` once it is full.
+    pub fn push(&mut self, bit: bool, out: &mut Vec<u8>) {
+        if bit {
+            self.current |= match self.order {
+                BitOrder::LsbFirst => 1 << self.filled,
+                BitOrder::MsbFirst => 0x80 >> self.filled,
+            };
+        }
+        self.filled += 1;
+
+        if self.filled == 8 {
+            out.push(self.current);
+            self.current = 0;
+            self.filled = 0;
         }
-        bytes.push(byte);
     }
 
-    file.write_all(&bytes)
-}
\ No newline at end of file
+    /// Appends the partial last byte, zero-padded, if there is one.
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        if self.filled > 0 {
+            out.push(self.current);
+            self.current = 0;
+            self.filled = 0;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitvec::prelude::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("huffman-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn read_reports_consumed_bytes_with_trailing_garbage() {
+        let path = temp_path("trailing.bits");
+        let bits = bitvec![u8, Lsb0; 1, 0, 1, 1, 0, 0, 1, 0, 1, 1, 1];
+        write_bits_to_file(path.clone(), &HashMap::new(), &bits).unwrap();
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"trailing garbage").unwrap();
+        drop(file);
+
+        let (_, read, consumed) = read_huffman_file(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(consumed, 2);
+        assert_eq!(read.len(), bits.len());
+        assert!(read.iter().by_vals().eq(bits.iter().by_vals()));
+    }
+
+    #[test]
+    fn write_pads_last_byte_with_zeros() {
+        let path = temp_path("padded.bits");
+        // Bits past the length are set in the backing byte
+        let mut bits = BitVec::<u8>::from_vec(vec![0xff]);
+        bits.truncate(3);
+        write_bits_to_file(path.clone(), &HashMap::new(), &bits).unwrap();
+
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(written.last(), Some(&0b111));
+    }
+
+    #[test]
+    fn read_rejects_short_payload() {
+        let path = temp_path("short.bits");
+        // Empty table, then a bit count with no payload behind it
+        fs::write(&path, [&[0, 0][..], &100u64.to_le_bytes()].concat()).unwrap();
+
+        let result = read_huffman_file(path.clone());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn file_decodes_with_only_its_stored_table() {
+        let path = temp_path("table.bits");
+        let input = b"a fresh process decodes this from the file alone".to_vec();
+        let huffman = crate::Huffman::from_bytes(input.clone());
+        write_bits_to_file(path.clone(), huffman.table(), &huffman.encode_bits().unwrap())
+            .unwrap();
+
+        let (table, bits, _) = read_huffman_file(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(table.codes().len(), huffman.table().len());
+        let fresh = crate::Huffman::from_serialized_table(&table).unwrap();
+        assert_eq!(fresh.decode_checked(&bits).unwrap(), input);
+    }
+
+    #[test]
+    fn bit_iter_matches_bitvec_views() {
+        let bytes = [0b1010_0110, 0xf0, 0x3c];
+
+        let lsb: Vec<bool> = BitIter::new(&bytes, 21, super::BitOrder::LsbFirst).collect();
+        let expected: Vec<bool> = bytes.view_bits::<Lsb0>()[..21].iter().by_vals().collect();
+        assert_eq!(lsb, expected);
+
+        let msb: Vec<bool> = BitIter::new(&bytes, 21, super::BitOrder::MsbFirst).collect();
+        let expected: Vec<bool> = bytes.view_bits::<Msb0>()[..21].iter().by_vals().collect();
+        assert_eq!(msb, expected);
+
+        assert_eq!(BitIter::new(&bytes, 100, super::BitOrder::LsbFirst).len(), 24);
+    }
+
+    #[test]
+    fn pack_unpack_roundtrip_in_both_orders() {
+        let bits: BitVec<u8> = bitvec![u8, Lsb0; 1, 1, 0, 1, 0, 0, 0, 0, 1, 0, 1];
+
+        let (lsb, count) = pack_bitvec(&bits, super::BitOrder::LsbFirst);
+        assert_eq!((lsb.as_slice(), count), ([0b0000_1011, 0b0000_0101].as_slice(), 11));
+        assert_eq!(unpack_bitvec(&lsb, count, super::BitOrder::LsbFirst), bits);
+
+        let (msb, count) = pack_bitvec(&bits, super::BitOrder::MsbFirst);
+        assert_eq!((msb.as_slice(), count), ([0b1101_0000, 0b1010_0000].as_slice(), 11));
+        assert_eq!(unpack_bitvec(&msb, count, super::BitOrder::MsbFirst), bits);
+
+        for len in [0usize, 1, 7, 8, 9, 64] {
+            let bits: BitVec<u8> = (0..len).map(|i| i % 3 == 0).collect();
+            for order in [super::BitOrder::LsbFirst, super::BitOrder::MsbFirst] {
+                let (bytes, count) = pack_bitvec(&bits, order);
+                assert_eq!(bytes.len(), len.div_ceil(8));
+                assert_eq!(unpack_bitvec(&bytes, count, order), bits);
+            }
+        }
+    }
+}
@@ -1,30 +1,89 @@
 #![allow(dead_code)]
 use std::path::PathBuf;
 
+mod archive;
+pub use archive::{
+    compress, compress_file, compress_file_region, compress_iter, compress_to_hex,
+    compress_with_codec, compress_with_mtf, compress_with_options, compress_with_scratch,
+    compress_with_target, decompress, decompress_all, decompress_borrowed, decompress_file,
+    decompress_from_hex, decompress_into, estimate_entropy_ratio, load_codec, merge_archives,
+    reconstruct_table, CompressionMode, CompressionOptions,
+};
+#[cfg(feature = "base64")]
+pub use archive::{compress_to_base64, decompress_from_base64};
+#[cfg(feature = "mmap")]
+pub use archive::compress_file_mmap;
+mod blocks;
+mod checksum;
+pub use blocks::{
+    compress_adaptive_blocks, compress_blocks, decompress_blocks, decompress_range, fixed_table,
+    BlockMode, HuffmanArchiveReader,
+};
+#[cfg(feature = "parallel")]
+pub use blocks::compress_blocks_parallel;
+mod frame;
+pub use frame::{read_frame, write_frame};
 mod huffman;
-use huffman::Huffman;
+pub use huffman::{
+    BalancedHuffman, BuildStep, CodeBuilder, CodecBuilder, EncodedData, FixedCodec,
+    GrowingCounter, Huffman, HuffmanError, HuffmanNode, HuffmanTable, ShannonFano,
+    StandardHuffman, SymbolDiff, TreeView,
+};
+mod integers;
+pub use integers::{compress_i32, compress_i64, decompress_i32, decompress_i64};
 mod io;
-use io::*;
+pub use io::{
+    pack_bits_ordered, pack_bitvec, read_bytes_file, read_huffman_file, unpack_bitvec,
+    write_bits_to_file, BitIter, BitOrder,
+};
+mod pipeline;
+pub use pipeline::{decompress_pipeline, Pipeline, TransformKind};
+mod reader;
+pub use reader::HuffmanReader;
+mod records;
+pub use records::{compress_fixed_width, decompress_fixed_width};
+mod stream;
+pub use stream::DecoderState;
+mod text;
+pub use text::{compress_text, decompress_text};
+#[cfg(feature = "timings")]
+mod timings;
+#[cfg(feature = "timings")]
+pub use timings::{profile, Timings};
+mod transform;
+pub use transform::{mtf_decode, mtf_encode, rle_decode, rle_encode, MtfDecoder, Transform};
+mod writer;
+pub use writer::HuffmanWriter;
 
 
+/// CLI entry point: `huffman [--keep | --delete] <input_file>`. Writes
+/// `<input_file>.huff` with `compress_file`. The original is kept unless
+/// `--delete` is given, in which case it is removed once the archive has
+/// been read back and verified; `--keep` is the default and wins over
+/// `--delete`, so gzip-style invocations work unchanged.
 pub fn run() {
-    let mut file_path = std::env::args()
-        .skip(1)
-        .next()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let delete_original = args.iter().any(|arg| arg == "--delete")
+        && !args.iter().any(|arg| arg == "--keep");
+    let file_path = args
+        .into_iter()
+        .find(|arg| arg != "--keep" && arg != "--delete")
+        .map(PathBuf::from)
         .expect("Couldn't parse the argument");
 
-    let input = read_string_file(PathBuf::from(&file_path))
-        .expect("Failed to read the file");
-    
-    let mut huffman = Huffman::new(&input);
-    let encoded = huffman.encode();
+    let original_len = std::fs::metadata(&file_path)
+        .expect("Failed to read the file")
+        .len();
+    let archive_path = compress_file(&file_path, delete_original)
+        .expect("Couldn't write the archive");
+    let archive_len = std::fs::metadata(&archive_path)
+        .expect("Couldn't read the archive back")
+        .len();
 
-    println!("Original size: {} bytes", input.len());
-    println!("Encoded size: {} bytes", encoded.len() / 8);
-
-    file_path.push_str(".huff");
-
-    write_bits_to_file(PathBuf::from(&file_path), &encoded)
-        .expect("Couldn't write to the file");
-
-}
\ No newline at end of file
+    println!("Original size: {} bytes", original_len);
+    println!("Archive size: {} bytes", archive_len);
+    if original_len > 0 {
+        let ratio = archive_len as f64 / original_len as f64;
+        println!("Compressed to {:.1}% of original", ratio * 100.0);
+    }
+}
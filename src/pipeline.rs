@@ -0,0 +1,171 @@
+use crate::archive::{compress, decompress, ByteReader};
+use crate::huffman::HuffmanError;
+use crate::transform::{mtf_decode, mtf_encode, rle_decode, rle_encode, Transform};
+
+
+pub const PIPELINE_MAGIC: [u8; 4] = *b"HUFP";
+
+/// One stage of a `Pipeline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformKind {
+    /// Run-length encoding (`rle_encode`).
+    Rle,
+    /// Move-to-front (`mtf_encode`).
+    Mtf,
+    /// A full Huffman archive (`compress`), table included.
+    Huffman,
+}
+
+impl TransformKind {
+    fn selector(self) -> u8 {
+        match self {
+            TransformKind::Rle => 0,
+            TransformKind::Mtf => 1,
+            TransformKind::Huffman => 2,
+        }
+    }
+
+    fn from_selector(selector: u8) -> Result<Self, HuffmanError> {
+        match selector {
+            0 => Ok(TransformKind::Rle),
+            1 => Ok(TransformKind::Mtf),
+            2 => Ok(TransformKind::Huffman),
+            _ => Err(HuffmanError::InvalidTransform(selector)),
+        }
+    }
+}
+
+impl Transform for TransformKind {
+    fn forward(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            TransformKind::Rle => rle_encode(data),
+            TransformKind::Mtf => mtf_encode(data),
+            TransformKind::Huffman => compress(data).0,
+        }
+    }
+
+    fn inverse(&self, data: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        match self {
+            TransformKind::Rle => rle_decode(data),
+            TransformKind::Mtf => Ok(mtf_decode(data)),
+            TransformKind::Huffman => decompress(data),
+        }
+    }
+}
+
+/// A chain of transforms applied in order, e.g. RLE → MTF → Huffman or
+/// any subset. The output header lists the stages, so
+/// `decompress_pipeline` can undo them without being told.
+///
+/// Format: magic, a stage count byte, one selector byte per stage, then
+/// the transformed data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    stages: Vec<TransformKind>,
+}
+
+impl Pipeline {
+    /// Fails with `TooManyStages` past 255 stages, as the count is
+    /// stored in one byte.
+    pub fn new(stages: &[TransformKind]) -> Result<Self, HuffmanError> {
+        if stages.len() > u8::MAX as usize {
+            return Err(HuffmanError::TooManyStages(stages.len()));
+        }
+
+        Ok(Pipeline { stages: stages.to_vec() })
+    }
+
+    pub fn stages(&self) -> &[TransformKind] {
+        &self.stages
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        output.extend_from_slice(&PIPELINE_MAGIC);
+        output.push(self.stages.len() as u8);
+        output.extend(self.stages.iter().map(|stage| stage.selector()));
+
+        let transformed = self
+            .stages
+            .iter()
+            .fold(data.to_vec(), |data, stage| stage.forward(&data));
+        output.extend(transformed);
+
+        output
+    }
+
+    /// Reads the stage list from the front of a pipeline archive,
+    /// returning it with the header length.
+    pub fn read_header(bytes: &[u8]) -> Result<(Self, usize), HuffmanError> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.take(PIPELINE_MAGIC.len())? != PIPELINE_MAGIC {
+            return Err(HuffmanError::InvalidMagic);
+        }
+
+        let count = reader.take_u8()? as usize;
+        let stages = reader
+            .take(count)?
+            .iter()
+            .map(|selector| TransformKind::from_selector(*selector))
+            .collect::<Result<_, _>>()?;
+
+        Ok((Pipeline { stages }, reader.pos))
+    }
+}
+
+/// Undoes `Pipeline::compress`, applying the recorded stages' inverses
+/// in reverse order.
+pub fn decompress_pipeline(bytes: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    let (pipeline, header_len) = Pipeline::read_header(bytes)?;
+
+    pipeline
+        .stages
+        .iter()
+        .rev()
+        .try_fold(bytes[header_len..].to_vec(), |data, stage| stage.inverse(&data))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_then_huffman_roundtrip() {
+        let input = [b"header".as_slice(), &[0; 2000], b"aaaaabbbbbbbbcc", &[0xff; 300]].concat();
+        let pipeline = Pipeline::new(&[TransformKind::Rle, TransformKind::Huffman]).unwrap();
+
+        let archive = pipeline.compress(&input);
+        assert!(archive.len() < input.len() / 10);
+
+        let (recorded, _) = Pipeline::read_header(&archive).unwrap();
+        assert_eq!(recorded.stages(), [TransformKind::Rle, TransformKind::Huffman]);
+        assert_eq!(decompress_pipeline(&archive).unwrap(), input);
+    }
+
+    #[test]
+    fn every_stage_and_no_stage_roundtrip() {
+        let input = b"mississippi banana bandana".repeat(8);
+        let all = [TransformKind::Rle, TransformKind::Mtf, TransformKind::Huffman];
+
+        for stages in [&all[..], &[]] {
+            let archive = Pipeline::new(stages).unwrap().compress(&input);
+            assert_eq!(decompress_pipeline(&archive).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_transform() {
+        let archive = [&PIPELINE_MAGIC[..], &[1, 9]].concat();
+
+        assert_eq!(decompress_pipeline(&archive), Err(HuffmanError::InvalidTransform(9)));
+    }
+
+    #[test]
+    fn stage_count_must_fit_a_byte() {
+        let stages = [TransformKind::Mtf; 256];
+
+        assert_eq!(Pipeline::new(&stages[..255]).unwrap().stages().len(), 255);
+        assert_eq!(Pipeline::new(&stages), Err(HuffmanError::TooManyStages(256)));
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{self, Read};
+
+use crate::archive::PayloadDecoder;
+use crate::huffman::HuffmanTable;
+
+
+/// Compressed bytes pulled from `inner` per read.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// Streaming decoder, the counterpart of `HuffmanWriter`: pulls packed
+/// bits (LSB-first) from `inner` and yields decoded bytes through `Read`,
+/// so a decoded stream can be piped on without holding all of it. Codes
+/// may span byte and chunk boundaries; a partial code is carried over.
+///
+/// Needs the code table and the bit count, e.g. from `read_huffman_file`
+/// or `HuffmanWriter::bit_count`. Reads stop at the byte holding the last
+/// bit, so anything after it (e.g. a footer) is left unread in `inner`.
+/// Fails with `UnexpectedEof` if `inner` ends first, or `InvalidData` for
+/// a bit sequence with no code.
+pub struct HuffmanReader<R: Read> {
+    inner: R,
+    decoder: PayloadDecoder,
+    bits_left: usize,
+    /// Payload bytes not yet read from `inner`.
+    bytes_left: usize,
+    buffer: Vec<u8>,
+    pos: usize,
+    filled: usize,
+    bit: u8,
+}
+
+impl<R: Read> HuffmanReader<R> {
+    pub fn new(inner: R, table: &HuffmanTable, bit_count: usize) -> Self {
+        HuffmanReader {
+            inner,
+            decoder: PayloadDecoder::new(table.codes().clone()),
+            bits_left: bit_count,
+            bytes_left: bit_count.div_ceil(8),
+            buffer: vec![0; READ_CHUNK_LEN.min(bit_count.div_ceil(8))],
+            pos: 0,
+            filled: 0,
+            bit: 0,
+        }
+    }
+
+    /// Bits not yet decoded.
+    pub fn bits_left(&self) -> usize {
+        self.bits_left
+    }
+
+    /// The inner reader, positioned after the payload once it has all
+    /// been decoded.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// The next payload bit, or `None` if `inner` has run out.
+    fn next_bit(&mut self) -> io::Result<Option<bool>> {
+        if self.pos == self.filled {
+            let want = self.buffer.len().min(self.bytes_left);
+            self.filled = self.inner.read(&mut self.buffer[..want])?;
+            self.bytes_left -= self.filled;
+            self.pos = 0;
+            if self.filled == 0 {
+                return Ok(None);
+            }
+        }
+
+        let bit = self.buffer[self.pos] >> self.bit & 1 == 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+
+        Ok(Some(bit))
+    }
+}
+
+impl<R: Read> Read for HuffmanReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < buf.len() && self.bits_left > 0 {
+            let Some(bit) = self.next_bit()? else {
+                if written > 0 {
+                    // Report the error on the next call
+                    return Ok(written);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "payload is shorter than its bit count",
+                ));
+            };
+            self.bits_left -= 1;
+
+            if let Some(byte) = self.decoder.push_bit(bit)? {
+                buf[written] = byte;
+                written += 1;
+            }
+        }
+
+        if self.bits_left == 0 {
+            self.decoder.finish()?;
+        }
+
+        Ok(written)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::Huffman;
+    use crate::io::pack_bits;
+
+    fn decode_table(huffman: &Huffman) -> HuffmanTable {
+        HuffmanTable::new(
+            huffman
+                .table()
+                .iter()
+                .map(|(byte, code)| (code.iter().by_vals().collect(), *byte))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn decodes_through_small_read_buffer() {
+        let input = b"decoded sixty-four bytes at a time, codes spanning bytes".repeat(30);
+        let huffman = Huffman::from_bytes(input.clone());
+        let bits = huffman.encode_bytes(&input).unwrap();
+        let packed = pack_bits(&bits);
+
+        let mut reader = HuffmanReader::new(&packed[..], &decode_table(&huffman), bits.len());
+        let mut output = Vec::new();
+        let mut buf = [0u8; 64];
+        loop {
+            let read = reader.read(&mut buf).unwrap();
+            if read == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..read]);
+        }
+
+        assert_eq!(output, input);
+        assert_eq!(reader.bits_left(), 0);
+    }
+
+    #[test]
+    fn leaves_trailing_bytes_unread() {
+        let input = b"payload then footer".repeat(5);
+        let huffman = Huffman::from_bytes(input.clone());
+        let bits = huffman.encode_bytes(&input).unwrap();
+        let stream = [pack_bits(&bits), b"FOOTER".to_vec()].concat();
+
+        let mut reader = HuffmanReader::new(&stream[..], &decode_table(&huffman), bits.len());
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+
+        assert_eq!(output, input);
+        assert_eq!(reader.into_inner(), b"FOOTER");
+    }
+
+    #[test]
+    fn reports_truncated_payload() {
+        let input = b"cut short".repeat(10);
+        let huffman = Huffman::from_bytes(input.clone());
+        let bits = huffman.encode_bytes(&input).unwrap();
+        let packed = pack_bits(&bits);
+
+        let cut = &packed[..packed.len() / 2];
+        let mut reader = HuffmanReader::new(cut, &decode_table(&huffman), bits.len());
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
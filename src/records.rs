@@ -0,0 +1,68 @@
+use crate::archive::{compress_with_options, decompress, CompressionOptions};
+use crate::huffman::HuffmanError;
+
+
+/// Compresses each value into its own archive and zero-pads it to
+/// exactly `width` bytes, for columnar stores with fixed-size cells.
+/// Archives use varint lengths to keep them small, and decompression
+/// ignores the padding since the header records the payload length.
+/// Fails with `RecordTooWide` if any value's archive exceeds `width`.
+pub fn compress_fixed_width(values: &[Vec<u8>], width: usize) -> Result<Vec<u8>, HuffmanError> {
+    let options = CompressionOptions { varint_lengths: true, ..Default::default() };
+    let mut records = Vec::with_capacity(values.len() * width);
+
+    for (index, value) in values.iter().enumerate() {
+        let archive = compress_with_options(value, &options);
+        if archive.len() > width {
+            return Err(HuffmanError::RecordTooWide { index, len: archive.len(), width });
+        }
+
+        records.extend_from_slice(&archive);
+        records.resize(records.len() + width - archive.len(), 0);
+    }
+
+    Ok(records)
+}
+
+/// Splits `records` into `width`-byte cells and decompresses each.
+/// Fails with `InvalidLength` if `records` isn't a whole number of cells.
+pub fn decompress_fixed_width(records: &[u8], width: usize)
+    -> Result<Vec<Vec<u8>>, HuffmanError> {
+    if width == 0 || !records.len().is_multiple_of(width) {
+        return Err(HuffmanError::InvalidLength);
+    }
+
+    records.chunks(width).map(decompress).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_width_roundtrip() {
+        let values = vec![
+            b"alpha".to_vec(),
+            b"".to_vec(),
+            b"mississippi mississippi".to_vec(),
+            vec![7; 40],
+        ];
+
+        let records = compress_fixed_width(&values, 64).unwrap();
+
+        assert_eq!(records.len(), values.len() * 64);
+        assert_eq!(decompress_fixed_width(&records, 64).unwrap(), values);
+        assert_eq!(decompress_fixed_width(&records[..100], 64), Err(HuffmanError::InvalidLength));
+    }
+
+    #[test]
+    fn rejects_values_wider_than_a_record() {
+        let values = vec![b"ok".to_vec(), b"this one will not fit in sixteen bytes".to_vec()];
+
+        assert!(matches!(
+            compress_fixed_width(&values, 16),
+            Err(HuffmanError::RecordTooWide { index: 1, width: 16, .. })
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::archive::{check_checksum, check_symbol_count, read_header, ArchiveMode, PayloadDecoder};
+use crate::checksum::Crc32;
+use crate::huffman::HuffmanError;
+use crate::transform::MtfDecoder;
+
+
+const DEFAULT_OUTPUT_CAPACITY: usize = 64 * 1024;
+
+/// Pull-based archive decoder: compressed bytes are pushed in with `feed`
+/// in chunks of any size, and decoded bytes are pulled out with
+/// `next_output` as soon as they are available. Header bytes are held
+/// until the whole header has arrived, and codes split across chunk
+/// boundaries are carried over to the next `feed`.
+///
+/// Decoded output is buffered up to a capacity; once it is full, `feed`
+/// stops accepting input until `next_output` drains it.
+pub struct DecoderState {
+    header_bytes: Vec<u8>,
+    payload: Option<PayloadState>,
+    output: Vec<u8>,
+    output_capacity: usize,
+}
+
+struct PayloadState {
+    mode: ArchiveMode,
+    decoder: PayloadDecoder,
+    mtf: MtfDecoder,
+    bits_left: usize,
+    expected_symbols: Option<usize>,
+    symbols: usize,
+    crc: Crc32,
+    /// Bytes of the CRC-32 footer received so far, while one is expected.
+    footer: Option<Vec<u8>>,
+}
+
+impl DecoderState {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_OUTPUT_CAPACITY)
+    }
+
+    /// `output_capacity` bounds how many decoded bytes are buffered before
+    /// `feed` applies backpressure. A single input byte can decode to up to
+    /// eight symbols, so the buffer may overshoot by that much.
+    pub fn with_capacity(output_capacity: usize) -> Self {
+        DecoderState {
+            header_bytes: Vec::new(),
+            payload: None,
+            output: Vec::new(),
+            output_capacity: output_capacity.max(1),
+        }
+    }
+
+    /// Accepts the next chunk of the archive and returns how many of its
+    /// bytes were consumed. Fewer than `bytes.len()` means the output
+    /// buffer is full: drain it with `next_output` and feed the rest again.
+    /// Bytes past the end of the payload are consumed and ignored.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<usize, HuffmanError> {
+        if self.payload.is_some() {
+            return self.feed_payload(bytes);
+        }
+
+        let buffered = self.header_bytes.len();
+        self.header_bytes.extend_from_slice(bytes);
+        match read_header(&self.header_bytes) {
+            Ok((header, header_len)) => {
+                self.payload = Some(PayloadState {
+                    mode: header.mode,
+                    decoder: PayloadDecoder::new(header.table),
+                    mtf: MtfDecoder::new(),
+                    bits_left: header.bit_count,
+                    expected_symbols: header.symbol_count,
+                    symbols: 0,
+                    crc: Crc32::new(),
+                    footer: header.checksum.then(Vec::new),
+                });
+                self.header_bytes.clear();
+
+                let header_part = header_len - buffered;
+                let payload_part = self.feed_payload(&bytes[header_part..])?;
+                Ok(header_part + payload_part)
+            }
+            // Wait for more of the header
+            Err(HuffmanError::Truncated) => Ok(bytes.len()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Takes every byte decoded so far, or `None` if nothing new is ready.
+    pub fn next_output(&mut self) -> Option<Vec<u8>> {
+        if self.output.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.output))
+        }
+    }
+
+    /// Whether the whole payload, and the checksum footer if the archive
+    /// has one, has been received and decoded.
+    pub fn is_finished(&self) -> bool {
+        self.payload
+            .as_ref()
+            .is_some_and(|payload| payload.bits_left == 0 && payload.footer.is_none())
+    }
+
+    /// Feeds the payload, then the footer once the payload is done. The
+    /// checksum is updated as bytes are decoded, so it is ready as soon as
+    /// the footer arrives.
+    fn feed_payload(&mut self, bytes: &[u8]) -> Result<usize, HuffmanError> {
+        let consumed = self.feed_data(bytes)?;
+        let payload = self.payload.as_mut().unwrap();
+        if payload.bits_left > 0 {
+            return Ok(consumed);
+        }
+
+        if let Some(footer) = &mut payload.footer {
+            let rest = &bytes[consumed..];
+            let take = rest.len().min(4 - footer.len());
+            footer.extend_from_slice(&rest[..take]);
+
+            if footer.len() == 4 {
+                let expected = u32::from_le_bytes(footer[..].try_into().unwrap());
+                check_checksum(expected, payload.crc.finish())?;
+                payload.footer = None;
+            }
+        }
+
+        Ok(bytes.len())
+    }
+
+    /// Decodes payload bytes, returning how many were used; stops at the
+    /// end of the payload or when the output buffer is full.
+    fn feed_data(&mut self, bytes: &[u8]) -> Result<usize, HuffmanError> {
+        let payload = self.payload.as_mut().unwrap();
+
+        if payload.mode == ArchiveMode::Stored {
+            let room = self.output_capacity.saturating_sub(self.output.len());
+            let take = bytes.len().min(payload.bits_left / 8).min(room);
+            self.output.extend_from_slice(&bytes[..take]);
+            payload.crc.update(&bytes[..take]);
+            payload.bits_left -= take * 8;
+            payload.symbols += take;
+
+            if payload.bits_left == 0 {
+                check_symbol_count(payload.expected_symbols, payload.symbols)?;
+            }
+
+            return Ok(take);
+        }
+
+        for (consumed, byte) in bytes.iter().enumerate() {
+            if payload.bits_left == 0 {
+                return Ok(consumed);
+            }
+            if self.output.len() >= self.output_capacity {
+                return Ok(consumed);
+            }
+
+            for i in 0..8.min(payload.bits_left) {
+                if let Some(decoded) = payload.decoder.push_bit(byte & (1 << i) != 0)? {
+                    let decoded = match payload.mode {
+                        ArchiveMode::MtfHuffman => payload.mtf.decode(decoded),
+                        _ => decoded,
+                    };
+                    payload.crc.push(decoded);
+                    self.output.push(decoded);
+                    payload.symbols += 1;
+                }
+            }
+            payload.bits_left -= 8.min(payload.bits_left);
+
+            if payload.bits_left == 0 {
+                payload.decoder.finish()?;
+                check_symbol_count(payload.expected_symbols, payload.symbols)?;
+            }
+        }
+
+        Ok(bytes.len())
+    }
+}
+
+impl Default for DecoderState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{compress, compress_with_mtf, compress_with_options, CompressionOptions};
+
+    #[test]
+    fn decode_in_one_byte_chunks() {
+        let input = b"streaming decode, one byte at a time".repeat(10);
+        let archive = compress(&input).0;
+
+        let mut state = DecoderState::new();
+        let mut decoded = Vec::new();
+        for byte in &archive {
+            assert_eq!(state.feed(std::slice::from_ref(byte)).unwrap(), 1);
+            if let Some(output) = state.next_output() {
+                decoded.extend(output);
+            }
+        }
+
+        assert!(state.is_finished());
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn decode_in_one_chunk() {
+        let input = b"all at once";
+        let mut state = DecoderState::new();
+
+        let archive = compress(input).0;
+        assert_eq!(state.feed(&archive).unwrap(), archive.len());
+
+        assert_eq!(state.next_output().unwrap(), input);
+        assert_eq!(state.next_output(), None);
+    }
+
+    #[test]
+    fn full_output_buffer_applies_backpressure() {
+        let input = b"backpressure ".repeat(50);
+        let archive = compress(&input).0;
+
+        let mut state = DecoderState::with_capacity(16);
+        let mut rest = &archive[..];
+        let mut decoded = Vec::new();
+        while !state.is_finished() {
+            let consumed = state.feed(rest).unwrap();
+            rest = &rest[consumed..];
+
+            let output = state.next_output().unwrap();
+            assert!(output.len() < 16 + 8);
+            decoded.extend(output);
+        }
+
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn rejects_bad_magic_once_header_arrives() {
+        let mut state = DecoderState::new();
+
+        assert_eq!(state.feed(b"HU"), Ok(2));
+        assert_eq!(state.feed(b"XX"), Err(HuffmanError::InvalidMagic));
+    }
+
+    #[test]
+    fn decode_stored_archive_in_chunks() {
+        let input: Vec<u8> = (0..=255).cycle().take(2000).collect();
+        let archive = compress(&input).0;
+
+        let mut state = DecoderState::with_capacity(100);
+        let mut rest = &archive[..];
+        let mut decoded = Vec::new();
+        while !state.is_finished() {
+            let chunk = &rest[..rest.len().min(7)];
+            let consumed = state.feed(chunk).unwrap();
+            rest = &rest[consumed..];
+            decoded.extend(state.next_output().unwrap_or_default());
+        }
+
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn decode_mtf_archive_in_chunks() {
+        let input = b"aaaabbbbaaaaccccaaaa".repeat(20);
+        let archive = compress_with_mtf(&input);
+
+        let mut state = DecoderState::new();
+        let mut decoded = Vec::new();
+        for chunk in archive.chunks(3) {
+            state.feed(chunk).unwrap();
+            decoded.extend(state.next_output().unwrap_or_default());
+        }
+
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn checks_stored_symbol_count() {
+        let options = CompressionOptions { store_symbol_count: true, ..Default::default() };
+        let mut archive = compress_with_options(b"aaaaaaaabbc", &options);
+
+        let mut state = DecoderState::new();
+        assert_eq!(state.feed(&archive), Ok(archive.len()));
+        assert_eq!(state.next_output().unwrap(), b"aaaaaaaabbc");
+
+        // Turns the leading "aa" (1 1) into "b" (0 1)
+        let payload_start = archive.len() - 2;
+        archive[payload_start] ^= 1;
+
+        let mut state = DecoderState::new();
+        let expected = HuffmanError::SymbolCountMismatch { expected: 11, actual: 10 };
+        assert_eq!(state.feed(&archive), Err(expected));
+    }
+
+    #[test]
+    fn checks_checksum_footer_split_across_chunks() {
+        let options = CompressionOptions { checksum: true, ..Default::default() };
+        let input = b"checksummed while streaming".repeat(5);
+        let mut archive = compress_with_options(&input, &options);
+
+        let mut state = DecoderState::new();
+        let mut decoded = Vec::new();
+        for chunk in archive.chunks(3) {
+            assert_eq!(state.feed(chunk), Ok(chunk.len()));
+            decoded.extend(state.next_output().unwrap_or_default());
+        }
+        assert!(state.is_finished());
+        assert_eq!(decoded, input);
+
+        let last = archive.len() - 1;
+        archive[last] ^= 0xff;
+        let mut state = DecoderState::new();
+        assert!(matches!(state.feed(&archive), Err(HuffmanError::ChecksumMismatch { .. })));
+    }
+}
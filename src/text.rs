@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use bitvec::prelude::*;
+
+use crate::archive::{ByteReader, PayloadDecoder};
+use crate::huffman::{canonical_codes, check_code_lengths, code_lengths, HuffmanError};
+
+
+pub const TEXT_MAGIC: [u8; 4] = *b"HUFT";
+pub const TEXT_VERSION: u8 = 1;
+
+/// Compresses `s` with one code per Unicode scalar value rather than per
+/// byte, so a multi-byte character is a single symbol with its own
+/// frequency. Codes are canonical, so the header only stores each
+/// character's code length.
+///
+/// Layout: magic, version, u32 symbol count, (u32 scalar value, u8 code
+/// length) per symbol, u64 character count, u64 bit count, then the
+/// payload packed LSB-first.
+pub fn compress_text(s: &str) -> Vec<u8> {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+
+    let lengths = code_lengths(&counts);
+    let table = canonical_codes(&lengths);
+
+    let mut bits: BitVec<u8> = BitVec::new();
+    for c in s.chars() {
+        bits.extend(table[&c].iter().by_vals());
+    }
+
+    let mut archive = Vec::new();
+    archive.extend_from_slice(&TEXT_MAGIC);
+    archive.push(TEXT_VERSION);
+    archive.extend_from_slice(&(lengths.len() as u32).to_le_bytes());
+    for (c, len) in &lengths {
+        archive.extend_from_slice(&(*c as u32).to_le_bytes());
+        archive.push(*len as u8);
+    }
+    archive.extend_from_slice(&(s.chars().count() as u64).to_le_bytes());
+    archive.extend_from_slice(&(bits.len() as u64).to_le_bytes());
+    archive.extend(bits.into_vec());
+
+    archive
+}
+
+/// Undoes `compress_text`. Fails with `InvalidTable` if the header holds
+/// something other than scalar values, or `InvalidCodeLengths` if their
+/// lengths don't form a complete prefix code (see `from_code_lengths`).
+pub fn decompress_text(bytes: &[u8]) -> Result<String, HuffmanError> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(TEXT_MAGIC.len())? != TEXT_MAGIC {
+        return Err(HuffmanError::InvalidMagic);
+    }
+
+    let version = reader.take_u8()?;
+    if version != TEXT_VERSION {
+        return Err(HuffmanError::UnsupportedVersion(version));
+    }
+
+    let symbols = reader.take_u32()? as usize;
+    let mut lengths = Vec::with_capacity(symbols.min(bytes.len()));
+    for _ in 0..symbols {
+        let c = char::from_u32(reader.take_u32()?).ok_or(HuffmanError::InvalidTable)?;
+        lengths.push((c, reader.take_u8()? as usize));
+    }
+    check_code_lengths(&lengths)?;
+
+    let char_count = reader.take_u64()? as usize;
+    let bit_count = reader.take_u64()? as usize;
+    let payload = reader.take(bit_count.div_ceil(8))?;
+    let bits = &payload.view_bits::<Lsb0>()[..bit_count];
+
+    let codes = canonical_codes(&lengths)
+        .into_iter()
+        .map(|(c, code)| (code.iter().by_vals().collect(), c))
+        .collect();
+    let mut decoder = PayloadDecoder::new(codes);
+
+    let mut text = String::new();
+    for bit in bits.iter().by_vals() {
+        if let Some(c) = decoder.push_bit(bit)? {
+            text.push(c);
+        }
+    }
+    decoder.finish()?;
+
+    let decoded = text.chars().count();
+    if decoded != char_count {
+        return Err(HuffmanError::SymbolCountMismatch { expected: char_count, actual: decoded });
+    }
+
+    Ok(text)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_and_accents_roundtrip() {
+        let text = "Crème brûlée à la café 🍮🍮 — déjà vu! 🎉 naïve façade, ünïcödé 🍮".repeat(3);
+
+        let archive = compress_text(&text);
+        let decoded = decompress_text(&archive).unwrap();
+
+        assert_eq!(decoded.as_bytes(), text.as_bytes());
+    }
+
+    #[test]
+    fn each_character_is_one_symbol() {
+        let archive = compress_text("ééééé🎉");
+        let symbols = u32::from_le_bytes(archive[5..9].try_into().unwrap());
+
+        assert_eq!(symbols, 2);
+        assert_eq!(decompress_text(&archive).unwrap(), "ééééé🎉");
+    }
+
+    #[test]
+    fn empty_and_single_symbol_text() {
+        assert_eq!(decompress_text(&compress_text("")).unwrap(), "");
+        assert_eq!(decompress_text(&compress_text("ßßß")).unwrap(), "ßßß");
+    }
+
+    #[test]
+    fn rejects_invalid_scalar_in_table() {
+        let mut archive = compress_text("ab");
+        archive[9..13].copy_from_slice(&0xd800u32.to_le_bytes());
+
+        assert_eq!(decompress_text(&archive), Err(HuffmanError::InvalidTable));
+    }
+
+    #[test]
+    fn rejects_incomplete_or_duplicate_lengths() {
+        // "aab": a = 1 bit, b = 1 bit; entries are 5 bytes from offset 9
+        let archive = compress_text("aab");
+
+        let mut short = archive.clone();
+        short[13] = 2;
+        assert_eq!(decompress_text(&short), Err(HuffmanError::InvalidCodeLengths));
+
+        let mut duplicate = archive;
+        duplicate[14..18].copy_from_slice(&(b'a' as u32).to_le_bytes());
+        assert_eq!(decompress_text(&duplicate), Err(HuffmanError::InvalidCodeLengths));
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use crate::huffman::{Huffman, HuffmanError};
+
+
+/// Time spent in each phase of a `profile` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Counting bytes, building the tree and generating the table.
+    pub build: Duration,
+    pub encode: Duration,
+    pub decode: Duration,
+}
+
+/// Builds a codec for `data`, encodes it and decodes the result again,
+/// timing each phase, to show where time goes on a given input. Fails if
+/// the round trip does.
+pub fn profile(data: &[u8]) -> Result<Timings, HuffmanError> {
+    let start = Instant::now();
+    let mut huffman = Huffman::from_bytes(data.to_vec());
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    let encoded = huffman.encode()?;
+    let encode = start.elapsed();
+
+    let start = Instant::now();
+    huffman.decode_checked(&encoded)?;
+    let decode = start.elapsed();
+
+    Ok(Timings { build, encode, decode })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_times_every_phase() {
+        let data = b"where does the time go? ".repeat(200);
+        let timings = profile(&data).unwrap();
+
+        assert!(timings.build >= Duration::ZERO);
+        assert!(timings.encode >= Duration::ZERO);
+        assert!(timings.decode >= Duration::ZERO);
+    }
+}
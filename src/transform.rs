@@ -0,0 +1,121 @@
+use crate::huffman::HuffmanError;
+
+
+/// A reversible byte transform that can be chained in a `Pipeline`.
+pub trait Transform {
+    fn forward(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Undoes `forward`, failing on input `forward` can't have produced.
+    fn inverse(&self, data: &[u8]) -> Result<Vec<u8>, HuffmanError>;
+}
+
+/// Run-length encoding as `(run length, byte)` pairs, with runs capped at
+/// 255. Pays off on long runs and doubles everything else.
+pub fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for run in data.chunk_by(|a, b| a == b) {
+        for part in run.chunks(u8::MAX as usize) {
+            output.push(part.len() as u8);
+            output.push(part[0]);
+        }
+    }
+
+    output
+}
+
+/// Fails with `InvalidLength` if `data` isn't made of whole pairs.
+pub fn rle_decode(data: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+    if !data.len().is_multiple_of(2) {
+        return Err(HuffmanError::InvalidLength);
+    }
+
+    let mut output = Vec::new();
+    for pair in data.chunks(2) {
+        output.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+    }
+
+    Ok(output)
+}
+
+/// Move-to-front transform: each byte is replaced by its position in a
+/// list of recently seen bytes, then moved to the front. Data with local
+/// repetition turns into mostly small values (runs become zeros), which
+/// Huffman codes far more compactly.
+pub fn mtf_encode(data: &[u8]) -> Vec<u8> {
+    let mut order = initial_order();
+
+    data.iter()
+        .map(|byte| {
+            let index = order.iter().position(|entry| entry == byte).unwrap();
+            order.copy_within(0..index, 1);
+            order[0] = *byte;
+            index as u8
+        })
+        .collect()
+}
+
+pub fn mtf_decode(data: &[u8]) -> Vec<u8> {
+    let mut decoder = MtfDecoder::new();
+
+    data.iter().map(|index| decoder.decode(*index)).collect()
+}
+
+/// Incremental inverse of `mtf_encode`, for decoding a byte at a time.
+#[derive(Debug, Clone)]
+pub struct MtfDecoder {
+    order: [u8; 256],
+}
+
+impl MtfDecoder {
+    pub fn new() -> Self {
+        MtfDecoder { order: initial_order() }
+    }
+
+    pub fn decode(&mut self, index: u8) -> u8 {
+        let index = index as usize;
+        let byte = self.order[index];
+        self.order.copy_within(0..index, 1);
+        self.order[0] = byte;
+
+        byte
+    }
+}
+
+impl Default for MtfDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn initial_order() -> [u8; 256] {
+    std::array::from_fn(|i| i as u8)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtf_turns_runs_into_zeros() {
+        assert_eq!(mtf_encode(b"aaabbb"), vec![97, 0, 0, 98, 0, 0]);
+        assert_eq!(mtf_encode(b"abab"), vec![97, 98, 1, 1]);
+    }
+
+    #[test]
+    fn rle_roundtrip() {
+        let data: Vec<u8> = [b"aaab".as_slice(), &[0; 600], b"xyz"].concat();
+
+        assert_eq!(rle_encode(b"aaab"), vec![3, b'a', 1, b'b']);
+        assert_eq!(rle_decode(&rle_encode(&data)).unwrap(), data);
+        assert_eq!(rle_decode(&[3]), Err(HuffmanError::InvalidLength));
+    }
+
+    #[test]
+    fn mtf_roundtrip() {
+        let data: Vec<u8> = (0..=255).chain((0..=255).rev()).chain(*b"banana").collect();
+
+        assert_eq!(mtf_decode(&mtf_encode(&data)), data);
+    }
+}
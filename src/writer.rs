@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use bitvec::prelude::*;
+
+use crate::huffman::HuffmanError;
+use crate::io::{BitOrder, BitPacker};
+
+
+/// Streaming encoder: bytes written to it are replaced by their codes from
+/// a table built beforehand (e.g. by counting the input in a first pass)
+/// and packed LSB-first into `inner`, so large inputs never have to be
+/// held in memory. Bits that don't fill a byte yet are carried over to
+/// the next `write`.
+///
+/// The last, partial byte is padded with zeros and written by `finish`,
+/// or on drop, where errors are ignored. `flush` only passes on whole
+/// bytes, since padding mid-stream would corrupt the codes that follow.
+pub struct HuffmanWriter<W: Write> {
+    inner: Option<W>,
+    table: HashMap<u8, BitVec>,
+    /// Whole bytes not yet accepted by `inner`.
+    packed: Vec<u8>,
+    packer: BitPacker,
+    bit_count: usize,
+}
+
+impl<W: Write> HuffmanWriter<W> {
+    /// Takes the table with codes written as `0`s and `1`s, as
+    /// `HuffmanTable::invert` gives it. Fails with `InvalidTable` if a code
+    /// is empty or has any other character.
+    pub fn new(inner: W, table: &HashMap<u8, String>) -> Result<Self, HuffmanError> {
+        let mut codes = HashMap::with_capacity(table.len());
+        for (byte, code) in table {
+            let bits = code
+                .chars()
+                .map(|c| match c {
+                    '0' => Ok(false),
+                    '1' => Ok(true),
+                    _ => Err(HuffmanError::InvalidTable),
+                })
+                .collect::<Result<BitVec, _>>()?;
+            if bits.is_empty() {
+                return Err(HuffmanError::InvalidTable);
+            }
+            codes.insert(*byte, bits);
+        }
+
+        Ok(Self::with_codes(inner, &codes))
+    }
+
+    /// Like `new`, with a codec's own table (see `Huffman::table`).
+    pub fn with_codes(inner: W, table: &HashMap<u8, BitVec>) -> Self {
+        HuffmanWriter {
+            inner: Some(inner),
+            table: table.clone(),
+            packed: Vec::new(),
+            packer: BitPacker::new(BitOrder::LsbFirst),
+            bit_count: 0,
+        }
+    }
+
+    /// Bits written so far, excluding padding; a reader needs it to know
+    /// where the codes end.
+    pub fn bit_count(&self) -> usize {
+        self.bit_count
+    }
+
+    /// Writes out the partial last byte and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_partial()?;
+        let mut inner = self.inner.take().expect("inner writer is only taken here");
+        inner.flush()?;
+
+        Ok(inner)
+    }
+
+    fn inner_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("inner writer is only taken by finish")
+    }
+
+    fn write_packed(&mut self) -> io::Result<()> {
+        let packed = std::mem::take(&mut self.packed);
+        let result = self.inner_mut().write_all(&packed);
+        if result.is_err() {
+            // Keep the bytes so the next write retries them
+            self.packed = packed;
+        }
+
+        result
+    }
+
+    fn write_partial(&mut self) -> io::Result<()> {
+        self.packer.finish(&mut self.packed);
+        self.write_packed()
+    }
+}
+
+impl<W: Write> Write for HuffmanWriter<W> {
+    /// Fails with `InvalidInput` if the first byte has no code; a later
+    /// one without a code ends the write short, before that byte.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+
+        for byte in buf {
+            let Some(code) = self.table.get(byte) else {
+                if consumed == 0 {
+                    return Err(HuffmanError::UnexpectedSymbol(*byte).into());
+                }
+                break;
+            };
+
+            for bit in code.iter().by_vals() {
+                self.packer.push(bit, &mut self.packed);
+            }
+            self.bit_count += code.len();
+            consumed += 1;
+        }
+
+        self.write_packed()?;
+        Ok(consumed)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_packed()?;
+        self.inner_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for HuffmanWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_partial();
+            let _ = self.inner_mut().flush();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::{Huffman, HuffmanTable};
+    use crate::io::pack_bits;
+
+    #[test]
+    fn chunked_writes_match_single_shot_encode() {
+        let input = b"streamed in small chunks, packed across byte boundaries".repeat(20);
+        let huffman = Huffman::from_bytes(input.clone());
+
+        let mut writer = HuffmanWriter::with_codes(Vec::new(), huffman.table());
+        for chunk in input.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        let bit_count = writer.bit_count();
+        let output = writer.finish().unwrap();
+
+        let bits = huffman.encode_bytes(&input).unwrap();
+        assert_eq!(bit_count, bits.len());
+        assert_eq!(output, pack_bits(&bits));
+    }
+
+    #[test]
+    fn takes_table_as_code_strings() {
+        let input = b"code strings from an archive header".to_vec();
+        let archive = crate::archive::compress(&input).0;
+        let table = HuffmanTable::from_header(&archive).unwrap().invert().unwrap();
+
+        let mut writer = HuffmanWriter::new(Vec::new(), &table).unwrap();
+        writer.write_all(&input).unwrap();
+        let bit_count = writer.bit_count();
+        let output = writer.finish().unwrap();
+
+        let codec = crate::load_codec(&archive).unwrap();
+        assert_eq!(codec.decode_packed(&output, bit_count, BitOrder::LsbFirst).unwrap(), input);
+
+        let bad = HashMap::from([(b'a', "0".to_string()), (b'b', "1x".to_string())]);
+        assert_eq!(HuffmanWriter::new(Vec::new(), &bad).err(), Some(HuffmanError::InvalidTable));
+    }
+
+    #[test]
+    fn drop_writes_partial_byte() {
+        // a = 1, b = 0: three bits never fill a byte
+        let huffman = Huffman::from_bytes(b"aab".to_vec());
+        let mut output = Vec::new();
+
+        let mut writer = HuffmanWriter::with_codes(&mut output, huffman.table());
+        writer.write_all(b"aab").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(output, pack_bits(&huffman.encode_bytes(b"aab").unwrap()));
+    }
+
+    #[test]
+    fn stops_before_byte_without_code() {
+        let huffman = Huffman::from_bytes(b"ab".to_vec());
+        let mut writer = HuffmanWriter::with_codes(Vec::new(), huffman.table());
+
+        assert_eq!(writer.write(b"abz").unwrap(), 2);
+        let error = writer.write(b"z").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
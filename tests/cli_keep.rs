@@ -0,0 +1,67 @@
+//! Runs the binary on temporary files: by default and with `--keep` the
+//! original stays, with `--delete` it is removed after verification.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use huffman::{decompress, decompress_file};
+
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("huffman-cli-{}-{}", std::process::id(), name))
+}
+
+/// Runs the binary with `flags` on a fresh file named `name`, returning
+/// whether the original survived and what the archive decompresses to.
+fn run_cli(name: &str, flags: &[&str], input: &[u8]) -> (bool, Vec<u8>) {
+    let original = temp_path(name);
+    fs::write(&original, input).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_huffman"))
+        .args(flags)
+        .arg(&original)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let archive = temp_path(&format!("{}.huff", name));
+    let restored = decompress(&fs::read(&archive).unwrap()).unwrap();
+    let kept = original.exists();
+
+    fs::remove_file(&archive).unwrap();
+    if kept {
+        fs::remove_file(&original).unwrap();
+    }
+
+    (kept, restored)
+}
+
+#[test]
+fn original_is_kept_unless_delete_is_given() {
+    let input = b"kept by default, removed only with --delete\n".repeat(20);
+
+    assert_eq!(run_cli("default.txt", &[], &input), (true, input.clone()));
+    assert_eq!(run_cli("keep.txt", &["--keep"], &input), (true, input.clone()));
+    assert_eq!(run_cli("delete.txt", &["--delete"], &input), (false, input.clone()));
+    assert_eq!(run_cli("both.txt", &["--keep", "--delete"], &input), (true, input));
+}
+
+#[test]
+fn decompress_file_restores_cli_output() {
+    let input = b"written by the CLI, restored by the library\n".repeat(20);
+    let original = temp_path("restore.txt");
+    fs::write(&original, &input).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_huffman")).arg(&original).status().unwrap();
+    assert!(status.success());
+
+    let archive = temp_path("restore.txt.huff");
+    let restored = temp_path("restored.txt");
+    decompress_file(&archive, Some(&restored)).unwrap();
+    assert_eq!(fs::read(&restored).unwrap(), input);
+
+    for path in [&original, &archive, &restored] {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Counts heap allocations made while encoding and decoding. Lives in
+//! its own test binary since a global allocator applies to the whole
+//! executable.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use bitvec::prelude::*;
+use huffman::{compress, decompress_borrowed, Huffman};
+
+
+struct CountingAllocator;
+
+thread_local! {
+    // Per thread, so tests running in parallel don't see each other's
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn encode_loop_does_not_allocate() {
+    let input = b"count every allocation in the encode loop".repeat(100);
+    let huffman = Huffman::from_bytes(input.clone());
+    let max_code_len = huffman.table().values().map(|code| code.len()).max().unwrap();
+    let mut bits: BitVec = BitVec::with_capacity(input.len() * max_code_len);
+
+    let before = allocations();
+    huffman.encode_into(&input, &mut bits).unwrap();
+    let after = allocations();
+
+    assert_eq!(after - before, 0);
+    assert_eq!(huffman.decode_checked(&bits).unwrap(), input);
+}
+
+#[test]
+fn presized_encode_allocates_once() {
+    let input = b"a longer message so the growing buffer reallocates".repeat(200);
+    let huffman = Huffman::from_bytes(input.clone());
+
+    let before = allocations();
+    let mut grown = BitVec::new();
+    huffman.encode_into(&input, &mut grown).unwrap();
+    let growing = allocations() - before;
+
+    let before = allocations();
+    let presized = huffman.encode_bytes(&input).unwrap();
+    let sized = allocations() - before;
+
+    assert_eq!(presized, grown);
+    assert_eq!(sized, 1);
+    assert!(growing > sized);
+}
+
+#[test]
+fn borrowed_stored_decompress_does_not_allocate() {
+    let input: Vec<u8> = (0..=255).collect();
+    let archive = compress(&input).0;
+
+    let before = allocations();
+    let data = decompress_borrowed(&archive).unwrap();
+    let after = allocations();
+
+    assert_eq!(after - before, 0);
+    assert_eq!(*data, input[..]);
+}
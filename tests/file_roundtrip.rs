@@ -0,0 +1,43 @@
+//! Compresses a file to `.bits` and decodes it into a new file using only
+//! what was written to disk, as two separate runs of the tool would.
+
+use std::fs;
+use std::path::PathBuf;
+
+use huffman::{read_bytes_file, read_huffman_file, write_bits_to_file, Huffman};
+
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("huffman-it-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn file_to_file_roundtrip() {
+    let source = temp_path("source.bin");
+    let encoded = temp_path("source.bin.bits");
+    let decoded = temp_path("decoded.bin");
+
+    let mut input = b"file to file, through the code table stored on disk\n".repeat(50);
+    input.extend(0..=255);
+    fs::write(&source, &input).unwrap();
+
+    let bytes = read_bytes_file(source.clone()).unwrap();
+    let huffman = Huffman::from_bytes(bytes);
+    let bits = huffman.encode_bits().unwrap();
+    write_bits_to_file(encoded.clone(), huffman.table(), &bits).unwrap();
+    drop(huffman);
+
+    let (table, bits, consumed) = read_huffman_file(encoded.clone()).unwrap();
+    let codec = Huffman::from_serialized_table(&table).unwrap();
+    fs::write(&decoded, codec.decode_bits(&bits).unwrap()).unwrap();
+
+    let output = fs::read(&decoded).unwrap();
+    let encoded_len = fs::metadata(&encoded).unwrap().len() as usize;
+    for path in [&source, &encoded, &decoded] {
+        fs::remove_file(path).unwrap();
+    }
+
+    assert_eq!(output, input);
+    assert_eq!(consumed, bits.len().div_ceil(8));
+    assert!(encoded_len < input.len());
+}